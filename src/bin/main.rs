@@ -1,8 +1,15 @@
 use clap::Parser;
 use minesweeper_rs::{
-    Difficulty, FirstClickPolicy, {game, tui},
+    Difficulty, FirstClickPolicy, Neighbourhood, Topology,
+    stats::Stats,
+    strategy::StrategyKind,
+    {game, tui},
 };
 use std::io::Result;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_SAVE_FILE: &str = "minesweeper.sav";
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -12,7 +19,7 @@ struct Args {
     difficulty: Option<Difficulty>,
 
     #[arg(short, long, value_enum, default_value_t = FirstClickPolicy::GuaranteedZero)]
-    ///
+    /// How the first revealed cell is protected from mines
     first_click_policy: FirstClickPolicy,
 
     #[arg(long, default_value_t = 9)]
@@ -27,13 +34,54 @@ struct Args {
     /// Number of mines (ignored if difficulty is set)
     num_mines: usize,
 
+    #[arg(long, value_enum, default_value_t = Topology::Flat)]
+    /// How the edges join up - on a torus, neighbours wrap around the edges
+    topology: Topology,
+
+    #[arg(long, value_enum, default_value_t = Neighbourhood::Moore)]
+    /// Which cells a number counts: the 8 around it (moore), or another stencil
+    neighbourhood: Neighbourhood,
+
+    #[arg(long, default_value_t = 1)]
+    /// Number of layers of width x height cells - above 1, a 3D board
+    /// shown one layer at a time
+    depth: usize,
+
     #[arg(long)]
     /// List available difficulty presets and exit
     list_difficulties: bool,
 
+    #[arg(long)]
+    /// Print wins, streaks and best times for every board played, and exit
+    stats: bool,
+
     #[arg(long, default_value_t = false)]
     /// display bomb probabilities - in the status bar for cell under the cursor.
     display_bomb_prob: bool,
+
+    #[arg(long)]
+    /// Seed for mine placement - the same seed and first click give the same board
+    seed: Option<u64>,
+
+    #[arg(long, value_enum, default_value_t = StrategyKind::LowestProbability)]
+    /// Bot that plays the game when 'a' is pressed (or with --watch)
+    bot: StrategyKind,
+
+    #[arg(long, default_value_t = 500)]
+    /// Milliseconds between the bot's moves
+    bot_delay_ms: u64,
+
+    #[arg(long)]
+    /// Watch the bot play from the start
+    watch: bool,
+
+    #[arg(long)]
+    /// Resume a game saved with 's' (overrides the board options)
+    load: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Where 's' saves the game [default: the --load file, or minesweeper.sav]
+    save_file: Option<PathBuf>,
 }
 
 fn main() -> Result<()> {
@@ -47,22 +95,97 @@ fn main() -> Result<()> {
         std::process::exit(0);
     }
 
+    let stats_path = Stats::default_path();
+    if args.stats {
+        let Some(path) = &stats_path else {
+            println!("Error: neither XDG_DATA_HOME nor HOME is set");
+            std::process::exit(1);
+        };
+        match Stats::load(path) {
+            Ok(stats) => print!("{stats}"),
+            Err(err) => {
+                println!("Error: could not read {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &args.load {
+        let game = match game::Game::from_file(path) {
+            Ok(game) => game,
+            Err(err) => {
+                println!("Error: could not load {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        };
+        let save_file = args.save_file.unwrap_or_else(|| path.clone());
+        let mut tui = tui::Tui::new(game, args.display_bomb_prob, save_file)?
+            .with_stats(stats_path)
+            .with_bot(
+                args.bot,
+                Duration::from_millis(args.bot_delay_ms),
+                args.watch,
+            );
+        return tui.game_loop();
+    }
+
     let (width, height, num_mines) = if let Some(difficulty) = args.difficulty {
         difficulty.dimensions()
     } else {
         (args.width, args.height, args.num_mines)
     };
 
-    if width * height <= num_mines + 9 {
+    if args.depth == 0 {
+        println!("Error: A board needs at least one layer.");
+        std::process::exit(1);
+    }
+    let cells = width * height * args.depth;
+    // The first click and its neighbours are kept free of mines
+    let free_cells = match args.depth {
+        1 => args.neighbourhood.offsets().len() + 1,
+        _ => args.neighbourhood.offsets_3d().len() + 1,
+    };
+    if cells <= num_mines + free_cells {
         println!(
-            "Error: Too many mines! Need at least {min_cells} cells for {num_mines} mines (including 9 mine-free cells around first click).",
-            min_cells = num_mines + 10
+            "Error: Too many mines! Need at least {min_cells} cells for {num_mines} mines (including {free_cells} mine-free cells around first click).",
+            min_cells = num_mines + free_cells + 1
         );
         std::process::exit(1);
     }
+    if matches!(args.first_click_policy, FirstClickPolicy::NoGuess)
+        && num_mines as f64 > game::MAX_NO_GUESS_DENSITY * cells as f64
+    {
+        println!(
+            "Error: Too many mines for a no-guess board! Use at most {max_mines} mines on {cells} cells, or another first click policy.",
+            max_mines = (game::MAX_NO_GUESS_DENSITY * cells as f64) as usize,
+        );
+        std::process::exit(1);
+    }
+    if let Err(err) = game::Game::check_shape(args.topology, args.neighbourhood, height) {
+        println!("Error: {err}.");
+        std::process::exit(1);
+    }
 
-    let game = game::Game::new(width, height, num_mines, args.first_click_policy);
-    let mut tui = tui::Tui::new(game, args.display_bomb_prob)?;
+    let game = match args.seed {
+        Some(seed) => {
+            game::Game::with_seed(width, height, num_mines, args.first_click_policy, seed)
+        }
+        None => game::Game::new(width, height, num_mines, args.first_click_policy),
+    }
+    .with_topology(args.topology)
+    .with_neighbourhood(args.neighbourhood)
+    .with_depth(args.depth);
+    let save_file = args
+        .save_file
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SAVE_FILE));
+    let mut tui = tui::Tui::new(game, args.display_bomb_prob, save_file)?
+        .with_stats(stats_path)
+        .with_bot(
+            args.bot,
+            Duration::from_millis(args.bot_delay_ms),
+            args.watch,
+        );
 
     tui.game_loop()
 }
@@ -1,123 +1,445 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use minesweeper_rs::{
-    Difficulty, FirstClickPolicy,
-    game::{CellState, Game, GameState},
+    Difficulty, FirstClickPolicy, Neighbourhood, Topology,
+    game::{Game, GameState},
+    replay::Replay,
+    strategy::{Strategy, StrategyKind},
 };
-use rand::Rng;
-use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+/// Benchmark the minesweeper solver
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Play a number of games and report the solver's win rate
+    Bench {
+        #[command(flatten)]
+        board: BoardArgs,
+
+        #[arg(short, long, default_value_t = 1000)]
+        /// Number of games to play
+        num_games: usize,
+
+        #[arg(long, value_parser = parse_cell)]
+        /// First click as X,Y (random for each game if not set)
+        first_click: Option<(usize, usize)>,
+
+        #[arg(short, long, value_enum, num_args = 1.., default_values_t = [StrategyKind::LowestProbability])]
+        /// Bots to compare - each one plays the same boards
+        strategy: Vec<StrategyKind>,
+
+        #[arg(long, default_value_t = false)]
+        /// Print the board after every move
+        show_moves: bool,
+
+        #[arg(long)]
+        /// Write a replay of every lost game to this directory
+        replay_dir: Option<PathBuf>,
+    },
+    /// Win rate for every possible first click, as a grid for plot_heatmap.gp
+    Heatmap {
+        #[command(flatten)]
+        board: BoardArgs,
+
+        #[arg(short, long, default_value_t = 10000)]
+        /// Number of games to play per first click
+        num_games: usize,
+
+        #[arg(short, long, value_enum, default_value_t = StrategyKind::LowestProbability)]
+        /// Bot that plays the games
+        strategy: StrategyKind,
+
+        #[arg(short, long)]
+        /// Output file [default: SolverDat/heatmap_<board>_<policy>.txt]
+        output: Option<PathBuf>,
+    },
+    /// Difficulty of generated boards: the distribution of 3BV, ZiNi,
+    /// openings and islands
+    Boards {
+        #[command(flatten)]
+        board: BoardArgs,
+
+        #[arg(short, long, default_value_t = 1000)]
+        /// Number of boards to generate
+        num_games: usize,
+    },
+}
+
+#[derive(Args, Debug)]
+struct BoardArgs {
+    #[arg(short, long, value_enum)]
+    /// Use a classic difficulty preset (overrides width/height/mines)
+    difficulty: Option<Difficulty>,
+
+    #[arg(short, long, value_enum, default_value_t = FirstClickPolicy::GuaranteedZero)]
+    /// How the first revealed cell is protected from mines
+    first_click_policy: FirstClickPolicy,
+
+    #[arg(long, default_value_t = 9)]
+    /// Number of columns (ignored if difficulty is set)
+    width: usize,
+
+    #[arg(long, default_value_t = 9)]
+    /// Number of rows (ignored if difficulty is set)
+    height: usize,
+
+    #[arg(long, default_value_t = 10)]
+    /// Number of mines (ignored if difficulty is set)
+    num_mines: usize,
+
+    #[arg(long, value_enum, default_value_t = Topology::Flat)]
+    /// How the edges join up - on a torus, neighbours wrap around the edges
+    topology: Topology,
+
+    #[arg(long, value_enum, default_value_t = Neighbourhood::Moore)]
+    /// Which cells a number counts: the 8 around it (moore), or another stencil
+    neighbourhood: Neighbourhood,
+
+    #[arg(long, default_value_t = 1)]
+    /// Number of layers of width x height cells - above 1, a 3D board
+    depth: usize,
+
+    #[arg(long)]
+    /// Base seed - game i is played with seed + i, making runs reproducible
+    seed: Option<u64>,
+}
+
+impl BoardArgs {
+    /// Columns, rows in each layer and mines.
+    fn dimensions(&self) -> (usize, usize, usize) {
+        match self.difficulty {
+            Some(difficulty) => difficulty.dimensions(),
+            None => (self.width, self.height, self.num_mines),
+        }
+    }
+
+    /// Rows of every layer, one layer after another (see `Game::rows`).
+    fn rows(&self) -> usize {
+        self.dimensions().1 * self.depth
+    }
+
+    /// Name used in output file names, e.g. "beginner", "20x10x30",
+    /// "expert_torus_knight" or "beginner_3_layers"
+    fn name(&self) -> String {
+        let mut name = match self.difficulty {
+            Some(difficulty) => value_name(difficulty),
+            None => format!("{}x{}x{}", self.width, self.height, self.num_mines),
+        };
+        if self.topology != Topology::Flat {
+            name = format!("{name}_{}", value_name(self.topology));
+        }
+        if self.neighbourhood != Neighbourhood::Moore {
+            name = format!("{name}_{}", value_name(self.neighbourhood));
+        }
+        if self.depth > 1 {
+            name = format!("{name}_{}_layers", self.depth);
+        }
+        name
+    }
+}
+
+/// The clap name of a value, with '_' instead of '-' (e.g. "guaranteed_zero")
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().replace('-', "_"))
+        .unwrap_or_default()
+}
+
+fn parse_cell(s: &str) -> Result<(usize, usize), String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected X,Y but got '{s}'"))?;
+    let x = x.trim().parse().map_err(|e| format!("bad X '{x}': {e}"))?;
+    let y = y.trim().parse().map_err(|e| format!("bad Y '{y}': {e}"))?;
+    Ok((x, y))
+}
+
+/// Game `i` of a run after its first click, and the RNG for the rest of it.
+fn start_game(board: &BoardArgs, i: usize, first_click: Option<(usize, usize)>) -> (Game, StdRng) {
+    let (width, height, num_mines) = board.dimensions();
+    let rows = board.rows();
+    // The same RNG drives the board, the random first click and tie-breaks.
+    // The board and first click are drawn first, so every strategy
+    // gets the same games for the same seed.
+    let mut rng = match board.seed {
+        Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(i as u64)),
+        None => StdRng::from_rng(&mut rand::rng()),
+    };
+    let board_rng = StdRng::from_rng(&mut rng);
+    let mut game = Game::with_rng(
+        width,
+        height,
+        num_mines,
+        board.first_click_policy,
+        board_rng,
+    )
+    .with_topology(board.topology)
+    .with_neighbourhood(board.neighbourhood)
+    .with_depth(board.depth);
+
+    // Use provided coordinate or generate random one
+    let (first_x, first_y) =
+        first_click.unwrap_or_else(|| (rng.random_range(0..width), rng.random_range(0..rows)));
+    game.reveal(first_x, first_y);
+    (game, rng)
+}
 
 /// calculate win rate over given number of games
 fn benchmark_solver(
+    board: &BoardArgs,
+    strategy: &dyn Strategy,
     num_games: usize,
-    difficulty: Difficulty,
-    first_click_policy: FirstClickPolicy,
     first_click: Option<(usize, usize)>,
+    show_moves: bool,
+    on_finish: impl Fn(usize, &Game) + Sync,
 ) -> usize {
-    let (width, height, num_mines) = difficulty.dimensions();
     (0..num_games)
-        .into_iter()
         .into_par_iter()
-        .map(|_| {
-            let mut rng = rand::rng();
-            let mut game = Game::new(width, height, num_mines, first_click_policy);
-
-            // Use provided coordinate or generate random one
-            let (first_x, first_y) = first_click
-                .unwrap_or_else(|| (rng.random_range(0..width), rng.random_range(0..height)));
-            game.reveal(first_x, first_y);
-
+        .map(|i| {
+            let (mut game, mut rng) = start_game(board, i, first_click);
             while game.state == GameState::Playing {
-                println!("{game}");
-                let probs = game.calculate_all_bomb_probs();
-
-                // Find lowest probability among covered cells
-                let mut min_prob = f64::INFINITY;
-                for y in 0..height {
-                    for x in 0..width {
-                        if game.get_cell(x, y).state == CellState::Covered {
-                            let prob = probs[y * width + x];
-                            if prob < min_prob {
-                                min_prob = prob;
-                            }
-                        }
-                    }
+                if show_moves {
+                    println!("{game}");
                 }
-
-                // Collect all cells with that min probability
-                let mut candidates = Vec::new();
-                for y in 0..height {
-                    for x in 0..width {
-                        if game.get_cell(x, y).state == CellState::Covered {
-                            if (probs[y * width + x] - min_prob).abs() < 1e-12 {
-                                candidates.push((x, y));
-                            }
-                        }
-                    }
-                }
-
-                // Pick a random candidate
-                if candidates.is_empty() {
+                let Some(action) = strategy.next_action(&game, &mut rng) else {
                     break;
-                }
-                let &(xx, yy) = candidates.choose(&mut rng).unwrap();
-                game.reveal(xx, yy);
+                };
+                game.apply(action);
+            }
+            if show_moves {
+                println!("{game}");
             }
+            on_finish(i, &game);
 
             (game.state == GameState::Won) as usize
         })
         .sum()
 }
 
-// fn bench_random() {
-//     let num_games = 1000;
-//     let first_click_policy = FirstClickPolicy::Unprotected;
-//     //let first_click_policy = FirstClickPolicy::GuaranteedZero;
-//     for difficulty in [
-//         Difficulty::Beginner,
-//         Difficulty::Intermediate,
-//         Difficulty::Expert,
-//     ] {
-//         let first_click = None;
-//         let wins = benchmark_solver(num_games, difficulty, first_click_policy, first);
-//         println!(
-//             "Difficulty {difficulty:?}: Solver won {}/{} games ({:.2}%)",
-//             wins,
-//             num_games,
-//             wins as f64 / num_games as f64 * 100.0
-//         );
-//     }
-// }
+fn bench(
+    board: &BoardArgs,
+    strategy: &dyn Strategy,
+    num_games: usize,
+    first_click: Option<(usize, usize)>,
+    show_moves: bool,
+    replay_dir: Option<&Path>,
+) {
+    let done = AtomicUsize::new(0);
+    let on_finish = |i: usize, game: &Game| {
+        if let Some(dir) = replay_dir
+            && game.state == GameState::Lost
+            && let Some(replay) = Replay::from_game(game)
+        {
+            let path = dir.join(format!("{}_lost_{i}.replay", strategy.name()));
+            if let Err(err) = replay.save(&path) {
+                eprintln!("\nCould not write {}: {err}", path.display());
+            }
+        }
+        let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+        if n.is_multiple_of(100) || n == num_games {
+            eprint!("\rPlayed {n}/{num_games} games");
+        }
+    };
+    let wins = benchmark_solver(
+        board,
+        strategy,
+        num_games,
+        first_click,
+        show_moves,
+        on_finish,
+    );
+    eprintln!();
+    println!(
+        "{} {:?} {}: Solver won {}/{} games ({:.2}%)",
+        board.name(),
+        board.first_click_policy,
+        strategy.name(),
+        wins,
+        num_games,
+        wins as f64 / num_games as f64 * 100.0
+    );
+}
 
 /// Calculate win_rate for every possible 1st click
-fn _heatmap() {
-    let num_games = 10000;
-    //let first_click_policy = FirstClickPolicy::Unprotected;
-    //let first_click_policy = FirstClickPolicy::GuaranteedZero;
-    let first_click_policy = FirstClickPolicy::GuaranteedSafe;
-    //let difficulty = Difficulty::Intermediate;
-    //let difficulty = Difficulty::Expert;
-    let difficulty = Difficulty::Beginner;
-    let (width, height, _) = difficulty.dimensions();
+fn heatmap(
+    board: &BoardArgs,
+    strategy: &dyn Strategy,
+    num_games: usize,
+    output: Option<PathBuf>,
+) -> io::Result<()> {
+    let (width, rows) = (board.dimensions().0, board.rows());
+    let output = output.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "SolverDat/heatmap_{}_{}.txt",
+            board.name(),
+            value_name(board.first_click_policy)
+        ))
+    });
+    let mut out = BufWriter::new(File::create(&output)?);
 
     // Output for plotting
-    for y in (0..height).rev() {
+    for y in (0..rows).rev() {
         for x in 0..width {
-            let first_click = Some((x, y));
-            let wins = benchmark_solver(num_games, difficulty, first_click_policy, first_click);
+            eprint!("\rFirst click ({x},{y}) of {width}x{rows}");
+            let wins = benchmark_solver(board, strategy, num_games, Some((x, y)), false, |_, _| {});
             let win_rate = wins as f64 / num_games as f64 * 100.0;
-            // space between values, no trailing space at end of line
-            print!("{win_rate:.2} ");
+            // space after every value, as in the files in SolverDat/
+            write!(out, "{win_rate:.2} ")?;
         }
-        println!();
+        writeln!(out)?;
     }
+    eprintln!("\nWrote {}", output.display());
+    out.flush()
 }
 
-fn main() {
-    benchmark_solver(
-        1,
-        Difficulty::Beginner,
-        FirstClickPolicy::GuaranteedZero,
-        Some((0, 0)),
+/// Prints the 3BV, ZiNi, openings and islands of generated boards, and a
+/// histogram of the 3BV
+fn boards(board: &BoardArgs, num_games: usize) {
+    let metrics: Vec<[usize; 4]> = (0..num_games)
+        .into_par_iter()
+        .map(|i| {
+            let (game, _) = start_game(board, i, None);
+            [
+                game.bbbv().unwrap_or_default(),
+                game.zini().unwrap_or_default(),
+                game.openings().unwrap_or_default(),
+                game.islands().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    if metrics.is_empty() {
+        return;
+    }
+
+    println!(
+        "{} {:?}: {num_games} boards",
+        board.name(),
+        board.first_click_policy
     );
-    //heatmap();
+    println!(
+        "{:<9} {:>8} {:>5} {:>7} {:>5}",
+        "", "mean", "min", "median", "max"
+    );
+    let mut columns = Vec::new();
+    for (k, name) in ["3BV", "ZiNi", "openings", "islands"].iter().enumerate() {
+        let mut values: Vec<usize> = metrics.iter().map(|m| m[k]).collect();
+        values.sort_unstable();
+        let mean = values.iter().sum::<usize>() as f64 / values.len() as f64;
+        println!(
+            "{name:<9} {mean:>8.2} {:>5} {:>7} {:>5}",
+            values[0],
+            values[values.len() / 2],
+            values[values.len() - 1]
+        );
+        columns.push(values);
+    }
+
+    // 3BV histogram in at most 20 buckets
+    let bbbv = &columns[0];
+    let (min, max) = (bbbv[0], bbbv[bbbv.len() - 1]);
+    let bucket = (max - min + 1).div_ceil(20);
+    let mut counts = vec![0usize; (max - min) / bucket + 1];
+    for &v in bbbv {
+        counts[(v - min) / bucket] += 1;
+    }
+    let most = counts.iter().copied().max().unwrap_or(1);
+    println!("\n3BV histogram:");
+    for (b, &count) in counts.iter().enumerate() {
+        let from = min + b * bucket;
+        let label = if bucket == 1 {
+            from.to_string()
+        } else {
+            format!("{from}-{}", from + bucket - 1)
+        };
+        let bar = "#".repeat((count * 50).div_ceil(most));
+        println!("{label:>9} {count:>6} {bar}");
+    }
+}
+
+fn main() -> io::Result<()> {
+    let cli = Cli::parse();
+    let board = match &cli.command {
+        Command::Bench { board, .. }
+        | Command::Heatmap { board, .. }
+        | Command::Boards { board, .. } => board,
+    };
+    if board.depth == 0 {
+        eprintln!("Error: A board needs at least one layer.");
+        std::process::exit(1);
+    }
+    let (width, height, num_mines) = board.dimensions();
+    let rows = board.rows();
+    // The first click and its neighbours are kept free of mines
+    let free_cells = match board.depth {
+        1 => board.neighbourhood.offsets().len() + 1,
+        _ => board.neighbourhood.offsets_3d().len() + 1,
+    };
+    if width * rows <= num_mines + free_cells {
+        eprintln!(
+            "Error: Too many mines! Need at least {min_cells} cells for {num_mines} mines (including {free_cells} mine-free cells around first click).",
+            min_cells = num_mines + free_cells + 1
+        );
+        std::process::exit(1);
+    }
+    if let Err(err) = Game::check_shape(board.topology, board.neighbourhood, height) {
+        eprintln!("Error: {err}.");
+        std::process::exit(1);
+    }
+
+    match cli.command {
+        Command::Bench {
+            board,
+            num_games,
+            first_click,
+            strategy,
+            show_moves,
+            replay_dir,
+        } => {
+            if let Some((x, y)) = first_click
+                && (x >= width || y >= rows)
+            {
+                eprintln!("Error: first click ({x},{y}) is outside the {width}x{rows} board");
+                std::process::exit(1);
+            }
+            if let Some(dir) = &replay_dir {
+                std::fs::create_dir_all(dir)?;
+            }
+            for kind in strategy {
+                let strategy = kind.build();
+                let dir = replay_dir.as_deref();
+                bench(
+                    &board,
+                    strategy.as_ref(),
+                    num_games,
+                    first_click,
+                    show_moves,
+                    dir,
+                );
+            }
+            Ok(())
+        }
+        Command::Heatmap {
+            board,
+            num_games,
+            strategy,
+            output,
+        } => heatmap(&board, strategy.build().as_ref(), num_games, output),
+        Command::Boards { board, num_games } => {
+            boards(&board, num_games);
+            Ok(())
+        }
+    }
 }
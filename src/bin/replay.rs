@@ -0,0 +1,80 @@
+use clap::Parser;
+use minesweeper_rs::{
+    replay::Replay,
+    tui::{ReplaySpeed, Tui},
+    video,
+};
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+/// Play back a replay written with 'w' in the game or by main_solver, or a
+/// RAWVF/AVF video
+struct Args {
+    /// Replay file to play (.rawvf and .avf files are read as videos)
+    file: PathBuf,
+
+    #[arg(short, long, default_value_t = 1.0)]
+    /// Playback speed, e.g. 2 for twice the original speed
+    speed: f64,
+
+    #[arg(long, default_value_t = false)]
+    /// Step through the moves one key press at a time
+    step: bool,
+
+    #[arg(long)]
+    /// Convert to this file instead of playing: RAWVF if it ends in .rawvf,
+    /// else our replay format
+    convert: Option<PathBuf>,
+}
+
+fn has_extension(path: &Path, ext: &str) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let loaded = if has_extension(&args.file, "rawvf") || has_extension(&args.file, "avf") {
+        video::from_file(&args.file)
+    } else {
+        Replay::from_file(&args.file)
+    };
+    let replay = match loaded {
+        Ok(replay) => replay,
+        Err(err) => {
+            println!("Error: could not load {}: {err}", args.file.display());
+            std::process::exit(1);
+        }
+    };
+    let game = match replay.new_game() {
+        Ok(game) => game,
+        Err(err) => {
+            println!("Error: {} is not a valid board: {err}", args.file.display());
+            std::process::exit(1);
+        }
+    };
+    if let Some(out) = &args.convert {
+        return if has_extension(out, "rawvf") {
+            fs::write(out, video::write_rawvf(&replay))
+        } else {
+            replay.save(out)
+        };
+    }
+    if args.speed <= 0.0 {
+        println!("Error: speed must be positive");
+        std::process::exit(1);
+    }
+    let speed = if args.step {
+        ReplaySpeed::Step
+    } else {
+        ReplaySpeed::Factor(args.speed)
+    };
+
+    let save_file = args.file.with_extension("sav");
+    let mut tui = Tui::new(game, false, save_file)?;
+    tui.play_replay(&replay, speed)
+}
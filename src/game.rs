@@ -1,7 +1,11 @@
 use std::collections::HashSet;
 use std::fmt;
 
-use crate::{Constraint, FirstClickPolicy, solver};
+use crate::solver::Deductions;
+use crate::{Constraint, FirstClickPolicy, Neighbourhood, Topology, hex, solver};
+use clap::ValueEnum;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use std::fs;
 use std::io;
 use std::path::Path;
@@ -19,12 +23,55 @@ pub enum ParseGameError {
         actual: usize,
         row_index: usize,
     },
+    /// The layers of a 3D board in the input string have different numbers
+    /// of rows.
+    InconsistentLayerHeight {
+        expected: usize,
+        actual: usize,
+        layer_index: usize,
+    },
+    /// A saved board contains a character that is not a valid cell.
+    InvalidCell {
+        character: char,
+        row_index: usize,
+        col_index: usize,
+    },
+    /// A required header field is missing from a saved game.
+    MissingField(&'static str),
+    /// A header field in a saved game has a value that can't be parsed.
+    InvalidField { field: String, value: String },
+    /// The number of mines on a saved board doesn't match its header.
+    MineCountMismatch { expected: usize, actual: usize },
+    /// No layout of mines fits a player's view: its numbers contradict each
+    /// other or the mine count.
+    ImpossiblePosition,
+    /// A hex torus with an odd number of rows (per layer), see
+    /// `Game::check_shape`.
+    OddHexTorus { rows: usize },
 }
 
 impl fmt::Display for ParseGameError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ParseGameError::EmptyInput => write!(f, "Input text cannot be empty."),
+            ParseGameError::InvalidCell {
+                character,
+                row_index,
+                col_index,
+            } => write!(
+                f,
+                "Invalid cell '{}' at row {}, column {}",
+                character, row_index, col_index
+            ),
+            ParseGameError::MissingField(field) => write!(f, "Missing field '{}'", field),
+            ParseGameError::InvalidField { field, value } => {
+                write!(f, "Invalid value '{}' for field '{}'", value, field)
+            }
+            ParseGameError::MineCountMismatch { expected, actual } => write!(
+                f,
+                "Board has {} mines, but the header says {}",
+                actual, expected
+            ),
             ParseGameError::InconsistentRowLength {
                 expected,
                 actual,
@@ -34,6 +81,23 @@ impl fmt::Display for ParseGameError {
                 "Inconsistent row length at row {}: expected {}, but got {}",
                 row_index, expected, actual
             ),
+            ParseGameError::InconsistentLayerHeight {
+                expected,
+                actual,
+                layer_index,
+            } => write!(
+                f,
+                "Inconsistent layer height at layer {}: expected {} rows, but got {}",
+                layer_index, expected, actual
+            ),
+            ParseGameError::ImpossiblePosition => {
+                write!(f, "No layout of mines fits the numbers and the mine count")
+            }
+            ParseGameError::OddHexTorus { rows } => write!(
+                f,
+                "A hex torus needs an even number of rows, so the shifted rows line up, not {}",
+                rows
+            ),
         }
     }
 }
@@ -94,12 +158,38 @@ pub enum CellState {
     Flagged,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Cell {
     pub content: CellContent,
     pub state: CellState,
 }
 
+/// A player action on the cell at (x, y).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Reveal(usize, usize),
+    Flag(usize, usize),
+    Chord(usize, usize),
+}
+
+/// Something the player did, as recorded in the move log.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogEvent {
+    /// The cursor moved to (x, y) - only front ends log these.
+    Cursor(usize, usize),
+    Action(Action),
+    Undo,
+    Redo,
+}
+
+/// A move log entry, timed relative to `Game::start_time` (zero before the
+/// first click).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LogEntry {
+    pub time: Duration,
+    pub event: LogEvent,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum GameState {
     Playing,
@@ -107,21 +197,79 @@ pub enum GameState {
     Lost,
 }
 
+/// First line of a file written by `Game::save`.
+const SAVE_HEADER: &str = "minesweeper-rs save 1";
+
+/// Upper bounds on the boards generated, and the time spent, looking for one
+/// that `FirstClickPolicy::NoGuess` can solve by logic alone. If none is, the
+/// last one is kept and `no_guess_failed` says so.
+const MAX_NO_GUESS_ATTEMPTS: usize = 1_000;
+const NO_GUESS_TIME_LIMIT: Duration = Duration::from_secs(3);
+
+/// Highest share of the cells that can be mines with
+/// `FirstClickPolicy::NoGuess`. Above it, hardly any board is solvable by
+/// logic alone (about 1 in 400 expert boards with 120 mines, 25%).
+pub const MAX_NO_GUESS_DENSITY: f64 = 0.25;
+
+#[derive(Clone)]
 pub struct Game {
     board: Vec<Cell>,
     pub width: usize,
+    /// Rows in each layer of a 3D board (all of them on a flat board). This
+    /// used to count the rows of every layer; `rows()` still does.
     pub height: usize,
     pub num_mines: usize,
     pub state: GameState,
     first_click: bool,
     pub first_click_policy: FirstClickPolicy,
+    topology: Topology,
+    neighbourhood: Neighbourhood,
+    depth: usize, // layers, stacked one after another in the rows
     pub start_time: Option<Instant>,
     pub final_time: Option<Duration>,
+    rng: StdRng,        // used for mine placement
+    revealed: usize,    // number of revealed cells, kept in step with the board
+    mines_known: bool,  // false for player-view positions, which can't be played
+    history: Vec<Move>, // moves that can be undone, oldest first
+    future: Vec<Move>,  // undone moves that can be redone, most recent last
+    log: Vec<LogEntry>, // everything the player did, in order
+    /// Set once undo/redo (or other help) has been used - the time shouldn't
+    /// count toward records.
+    pub assisted: bool,
+    no_guess_failed: bool, // NoGuess gave up and dealt a board that may need a guess
+}
+
+/// The game-wide fields a move can change, alongside the cells.
+#[derive(Clone, Debug, PartialEq)]
+struct Status {
+    state: GameState,
+    first_click: bool,
+    revealed: usize,
+    start_time: Option<Instant>,
+    final_time: Option<Duration>,
+}
+
+/// A cell changed by a move: its index, and the cell before and after.
+type Change = (usize, Cell, Cell);
+
+/// A reversible move: the cells it changed, in order (a cell can change more
+/// than once), and the game status before and after it.
+#[derive(Clone, Debug)]
+struct Move {
+    changes: Vec<Change>,
+    before: Status,
+    after: Status,
 }
 
 impl fmt::Display for Game {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for y in 0..self.height {
+        for y in 0..self.rows() {
+            if y > 0 && y % self.height == 0 {
+                writeln!(f)?; // between the layers of a 3D board
+            }
+            if self.is_shifted(y) {
+                write!(f, " ")?; // half a cell
+            }
             for x in 0..self.width {
                 let cell = self.get_cell(x, y);
                 let representation = match cell.state {
@@ -145,7 +293,13 @@ impl fmt::Display for Game {
 
 impl Game {
     pub fn display_all(&self) {
-        for y in 0..self.height {
+        for y in 0..self.rows() {
+            if y > 0 && y % self.height == 0 {
+                println!();
+            }
+            if self.is_shifted(y) {
+                print!(" ");
+            }
             for x in 0..self.width {
                 let cell = self.get_cell(x, y);
                 let representation = match cell.content {
@@ -160,7 +314,8 @@ impl Game {
         }
     }
 
-    /// Reads a file and uses `from_text` to parse its content into a Game.
+    /// Reads a file and parses its content into a Game. Files written by
+    /// `save` are parsed with `from_save_text`, anything else with `from_text`.
     ///
     /// # Errors
     ///
@@ -168,7 +323,285 @@ impl Game {
     /// or if the file content is not a valid grid (`LoadGameError::Parse`).
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, LoadGameError> {
         let content = fs::read_to_string(path)?;
-        let game = Game::from_text(&content)?;
+        let game = if content.trim_start().starts_with(SAVE_HEADER) {
+            Game::from_save_text(&content)?
+        } else {
+            Game::from_text(&content)?
+        };
+        Ok(game)
+    }
+
+    /// Writes the full game state to a file, see `to_save_text`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_save_text())
+    }
+
+    /// Serialises the full game state: the mine layout, which cells are
+    /// revealed or flagged, the first-click policy and status, the topology,
+    /// neighbourhood and number of layers, the game state and the elapsed
+    /// time. The board uses one character per cell, with a blank line
+    /// between the layers of a 3D board:
+    ///
+    /// * `.` covered, `*` covered mine
+    /// * `f` flagged, `F` flagged mine
+    /// * `0`-`8` (or more) revealed number, `X` exploded mine
+    ///
+    /// # Example
+    ///
+    /// ```text
+    /// minesweeper-rs save 1
+    /// mines 2
+    /// policy guaranteed-zero
+    /// topology flat
+    /// neighbourhood moore
+    /// depth 1
+    /// first_click false
+    /// state playing
+    /// elapsed_ms 5120
+    /// assisted false
+    /// board
+    /// 0 1 *
+    /// 0 2 .
+    /// 0 1 F
+    /// ```
+    pub fn to_save_text(&self) -> String {
+        let policy = self
+            .first_click_policy
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default();
+        let state = match self.state {
+            GameState::Playing => "playing",
+            GameState::Won => "won",
+            GameState::Lost => "lost",
+        };
+        let elapsed_ms = self
+            .final_time
+            .or_else(|| self.start_time.map(|start| start.elapsed()))
+            .unwrap_or_default()
+            .as_millis();
+
+        let topology = self
+            .topology
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default();
+        let neighbourhood = self
+            .neighbourhood
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default();
+
+        let mut text = format!(
+            "{SAVE_HEADER}\nmines {}\npolicy {policy}\ntopology {topology}\nneighbourhood {neighbourhood}\ndepth {}\nfirst_click {}\nstate {state}\nelapsed_ms {elapsed_ms}\nassisted {}\nboard\n",
+            self.num_mines, self.depth, self.first_click, self.assisted
+        );
+        for y in 0..self.rows() {
+            if y > 0 && y % self.height == 0 {
+                text.push('\n'); // between the layers of a 3D board
+            }
+            let row: Vec<String> = (0..self.width)
+                .map(|x| {
+                    let cell = self.get_cell(x, y);
+                    let is_mine = cell.content == CellContent::Mine;
+                    match (cell.state, cell.content) {
+                        (CellState::Covered, _) if is_mine => "*".to_string(),
+                        (CellState::Covered, _) => ".".to_string(),
+                        (CellState::Flagged, _) if is_mine => "F".to_string(),
+                        (CellState::Flagged, _) => "f".to_string(),
+                        (CellState::Revealed, CellContent::Number(n)) => n.to_string(),
+                        (CellState::Revealed, _) => "X".to_string(),
+                    }
+                })
+                .collect();
+            text.push_str(&row.join(" "));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parses a game written by `to_save_text`, restoring the revealed and
+    /// flagged cells, first-click status, game state and elapsed time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err` if a header field is missing or invalid, the board is
+    /// not a valid grid, or its mine count disagrees with the header.
+    pub fn from_save_text(text: &str) -> Result<Self, ParseGameError> {
+        let mut lines = text.trim().lines().map(str::trim);
+        match lines.next() {
+            Some(SAVE_HEADER) => {}
+            Some(line) => {
+                return Err(ParseGameError::InvalidField {
+                    field: "header".to_string(),
+                    value: line.to_string(),
+                });
+            }
+            None => return Err(ParseGameError::EmptyInput),
+        }
+
+        let mut num_mines = None;
+        let mut first_click_policy = None;
+        let mut first_click = None;
+        let mut state = None;
+        let mut elapsed = None;
+        let mut assisted = false; // optional, for files saved before it was added
+        let mut topology = Topology::Flat; // likewise
+        let mut neighbourhood = Neighbourhood::Moore;
+        let mut depth = 1;
+        for line in lines.by_ref() {
+            if line == "board" {
+                break;
+            }
+            let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+            let invalid = || ParseGameError::InvalidField {
+                field: field.to_string(),
+                value: value.to_string(),
+            };
+            match field {
+                "mines" => num_mines = Some(value.parse::<usize>().map_err(|_| invalid())?),
+                "policy" => {
+                    first_click_policy =
+                        Some(FirstClickPolicy::from_str(value, true).map_err(|_| invalid())?)
+                }
+                "topology" => topology = Topology::from_str(value, true).map_err(|_| invalid())?,
+                "depth" => {
+                    depth = value.parse::<usize>().map_err(|_| invalid())?;
+                    if depth == 0 {
+                        return Err(invalid());
+                    }
+                }
+                "neighbourhood" => {
+                    neighbourhood = Neighbourhood::from_str(value, true).map_err(|_| invalid())?
+                }
+                "first_click" => first_click = Some(value.parse::<bool>().map_err(|_| invalid())?),
+                "state" => {
+                    state = Some(match value {
+                        "playing" => GameState::Playing,
+                        "won" => GameState::Won,
+                        "lost" => GameState::Lost,
+                        _ => return Err(invalid()),
+                    })
+                }
+                "elapsed_ms" => {
+                    let ms = value.parse::<u64>().map_err(|_| invalid())?;
+                    elapsed = Some(Duration::from_millis(ms))
+                }
+                "assisted" => assisted = value.parse::<bool>().map_err(|_| invalid())?,
+                _ => return Err(invalid()),
+            }
+        }
+        let num_mines = num_mines.ok_or(ParseGameError::MissingField("mines"))?;
+        let first_click_policy =
+            first_click_policy.ok_or(ParseGameError::MissingField("policy"))?;
+        let first_click = first_click.ok_or(ParseGameError::MissingField("first_click"))?;
+        let state = state.ok_or(ParseGameError::MissingField("state"))?;
+        let elapsed = elapsed.ok_or(ParseGameError::MissingField("elapsed_ms"))?;
+
+        let rows: Vec<Vec<&str>> = lines
+            .filter(|s| !s.is_empty())
+            .map(|s| s.split_whitespace().collect())
+            .collect();
+        if rows.is_empty() {
+            return Err(ParseGameError::EmptyInput);
+        }
+        let width = rows[0].len();
+        if !rows.len().is_multiple_of(depth) {
+            return Err(ParseGameError::InvalidField {
+                field: "depth".to_string(),
+                value: depth.to_string(),
+            });
+        }
+        let height = rows.len() / depth;
+        Game::check_shape(topology, neighbourhood, height)?;
+        let mut board = Vec::with_capacity(width * rows.len());
+        let mut mines_on_board = 0;
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(ParseGameError::InconsistentRowLength {
+                    expected: width,
+                    actual: row.len(),
+                    row_index: y,
+                });
+            }
+            for (x, token) in row.iter().enumerate() {
+                let (content, state) = match *token {
+                    "." => (CellContent::Number(0), CellState::Covered),
+                    "*" => (CellContent::Mine, CellState::Covered),
+                    "f" => (CellContent::Number(0), CellState::Flagged),
+                    "F" => (CellContent::Mine, CellState::Flagged),
+                    "X" => (CellContent::Mine, CellState::Revealed), // see below
+                    t if t.parse::<u8>().is_ok() => (CellContent::Number(0), CellState::Revealed),
+                    t => {
+                        return Err(ParseGameError::InvalidCell {
+                            character: t.chars().next().unwrap_or(' '),
+                            row_index: y,
+                            col_index: x,
+                        });
+                    }
+                };
+                if content == CellContent::Mine {
+                    mines_on_board += 1;
+                }
+                board.push(Cell { content, state });
+            }
+        }
+        // Before the first click the mines haven't been placed yet
+        if !first_click && mines_on_board != num_mines {
+            return Err(ParseGameError::MineCountMismatch {
+                expected: num_mines,
+                actual: mines_on_board,
+            });
+        }
+
+        let revealed = board
+            .iter()
+            .filter(|c| c.state == CellState::Revealed)
+            .count();
+        let (start_time, final_time) = match state {
+            _ if first_click => (None, None),
+            GameState::Playing => (Instant::now().checked_sub(elapsed), None),
+            _ => (Instant::now().checked_sub(elapsed), Some(elapsed)),
+        };
+        let mut game = Game {
+            board,
+            width,
+            height,
+            num_mines,
+            state,
+            first_click,
+            first_click_policy,
+            topology,
+            neighbourhood,
+            depth,
+            start_time,
+            final_time,
+            rng: StdRng::from_rng(&mut rand::rng()),
+            revealed,
+            mines_known: true,
+            history: Vec::new(),
+            future: Vec::new(),
+            log: Vec::new(),
+            assisted,
+            no_guess_failed: false,
+        };
+        // The mines have to fit around the first click when it comes
+        if first_click && num_mines + game.first_click_free_cells() > game.board.len() {
+            return Err(ParseGameError::InvalidField {
+                field: "mines".to_string(),
+                value: num_mines.to_string(),
+            });
+        }
+        if !first_click {
+            game.calculate_numbers();
+        }
+        // A revealed mine is the explosion - marked after the numbers are
+        // calculated, since only `Mine` counts towards them.
+        for cell in game.board.iter_mut() {
+            if cell.content == CellContent::Mine && cell.state == CellState::Revealed {
+                cell.content = CellContent::Explosion;
+            }
+        }
         Ok(game)
     }
 
@@ -179,10 +612,13 @@ impl Game {
     /// 'Covered' state. The function will automatically calculate the numbers
     /// for the safe cells based on adjacent mines.
     ///
+    /// Blank lines separate the layers of a 3D board, as `Display` writes
+    /// them, and each layer becomes `height` rows of the board.
+    ///
     /// # Errors
     ///
     /// This function will return an `Err` if the input text is not a valid grid
-    /// (e.g., if rows have different lengths or the input is empty).
+    /// (e.g., if rows or layers have different lengths or the input is empty).
     ///
     /// # Example
     ///
@@ -204,23 +640,40 @@ impl Game {
     /// assert_eq!(game.width, 5);
     /// assert_eq!(game.height, 3);
     /// assert_eq!(game.num_mines, 3);
+    ///
+    /// // Two layers of 2x2
+    /// let game = Game::from_text("*.\n..\n\n..\n.*").unwrap();
+    /// assert_eq!((game.width, game.height, game.depth()), (2, 2, 2));
+    /// assert_eq!(game.neighbours(0, 0).len(), 7);
     /// ```
     pub fn from_text(text: &str) -> Result<Self, ParseGameError> {
-        let lines: Vec<&str> = text
-            .trim()
-            .lines()
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
+        let lines: Vec<&str> = text.trim().lines().map(str::trim).collect();
+        let layers: Vec<&[&str]> = lines
+            .split(|line| line.is_empty())
+            .filter(|layer| !layer.is_empty())
             .collect();
 
-        if lines.is_empty() {
+        if layers.is_empty() {
             return Err(ParseGameError::EmptyInput);
         }
 
-        let height = lines.len();
+        let height = layers[0].len();
+        if let Some((layer_index, layer)) = layers
+            .iter()
+            .enumerate()
+            .find(|(_, layer)| layer.len() != height)
+        {
+            return Err(ParseGameError::InconsistentLayerHeight {
+                expected: height,
+                actual: layer.len(),
+                layer_index,
+            });
+        }
+        let depth = layers.len();
+        let lines = layers.concat();
         let width = lines[0].chars().count();
         let mut num_mines = 0;
-        let mut board = Vec::with_capacity(width * height);
+        let mut board = Vec::with_capacity(width * lines.len());
 
         for (y, line) in lines.iter().enumerate() {
             let current_width = line.chars().count();
@@ -254,8 +707,19 @@ impl Game {
             state: GameState::Playing,
             first_click: false, // normally mines are placed on first click
             first_click_policy: FirstClickPolicy::Unprotected,
+            topology: Topology::Flat,
+            neighbourhood: Neighbourhood::Moore,
+            depth,
             start_time: Some(Instant::now()),
             final_time: None,
+            rng: StdRng::from_rng(&mut rand::rng()),
+            revealed: 0,
+            mines_known: true,
+            history: Vec::new(),
+            future: Vec::new(),
+            log: Vec::new(),
+            assisted: false,
+            no_guess_failed: false,
         };
 
         game.calculate_numbers();
@@ -263,6 +727,132 @@ impl Game {
         Ok(game)
     }
 
+    /// Creates a position as a player sees it, for analysis: the numbers
+    /// that have been revealed and the cells that are still covered or
+    /// flagged, without knowing where the mines are.
+    ///
+    /// Each non-whitespace character is a cell and rows are separated by
+    /// newlines or '/':
+    ///
+    /// * `0`-`8` revealed number (`.` is the same as `0`)
+    /// * `#` covered
+    /// * `F` flagged (treated as covered by the solver)
+    ///
+    /// `num_mines` is the total number of mines on the board. The result
+    /// supports `get_constraints`, `get_deductions` and the probability
+    /// calculations, but `reveal` and `chord` do nothing, since what is under
+    /// the covered cells is unknown.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err` if the input is empty, rows have different lengths,
+    /// or contains a character that is not a cell, and `ImpossiblePosition`
+    /// if no layout of `num_mines` mines fits the numbers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use minesweeper_rs::game::Game;
+    ///
+    /// let game = Game::from_player_view("1 1 # / 1 # #", 1).unwrap();
+    /// let probs = game.calculate_exact_bomb_probs();
+    /// assert_eq!(probs[4], 1.0); // the mine is at (1,1)
+    /// ```
+    pub fn from_player_view(text: &str, num_mines: usize) -> Result<Self, ParseGameError> {
+        let rows: Vec<Vec<char>> = text
+            .split(['\n', '/'])
+            .map(|row| {
+                row.chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|row| !row.is_empty())
+            .collect();
+        if rows.is_empty() {
+            return Err(ParseGameError::EmptyInput);
+        }
+
+        let height = rows.len();
+        let width = rows[0].len();
+        let mut board = Vec::with_capacity(width * height);
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(ParseGameError::InconsistentRowLength {
+                    expected: width,
+                    actual: row.len(),
+                    row_index: y,
+                });
+            }
+            for (x, &character) in row.iter().enumerate() {
+                let cell = match character {
+                    '#' => Cell {
+                        content: CellContent::Number(0),
+                        state: CellState::Covered,
+                    },
+                    'F' => Cell {
+                        content: CellContent::Number(0),
+                        state: CellState::Flagged,
+                    },
+                    '.' => Cell {
+                        content: CellContent::Number(0),
+                        state: CellState::Revealed,
+                    },
+                    '0'..='8' => Cell {
+                        content: CellContent::Number(character as u8 - b'0'),
+                        state: CellState::Revealed,
+                    },
+                    _ => {
+                        return Err(ParseGameError::InvalidCell {
+                            character,
+                            row_index: y,
+                            col_index: x,
+                        });
+                    }
+                };
+                board.push(cell);
+            }
+        }
+
+        let revealed = board
+            .iter()
+            .filter(|c| c.state == CellState::Revealed)
+            .count();
+        let game = Game {
+            board,
+            width,
+            height,
+            num_mines,
+            state: GameState::Playing,
+            first_click: false,
+            first_click_policy: FirstClickPolicy::Unprotected,
+            topology: Topology::Flat,
+            neighbourhood: Neighbourhood::Moore,
+            depth: 1,
+            start_time: None,
+            final_time: None,
+            rng: StdRng::from_rng(&mut rand::rng()),
+            revealed,
+            mines_known: false,
+            history: Vec::new(),
+            future: Vec::new(),
+            log: Vec::new(),
+            assisted: false,
+            no_guess_failed: false,
+        };
+        // The analysis assumes some layout fits, and goes wrong if none does
+        let (global_constraint, local_constraints, _sea_of_unknown) = game.get_constraints();
+        if solver::solve_exact(&global_constraint, &local_constraints, width * height).is_none() {
+            return Err(ParseGameError::ImpossiblePosition);
+        }
+        Ok(game)
+    }
+
+    /// True for positions created by `from_player_view`, where the mine
+    /// layout is unknown and the game can only be analysed, not played.
+    pub fn is_analysis(&self) -> bool {
+        !self.mines_known
+    }
+
     pub fn get_cell(&self, x: usize, y: usize) -> &Cell {
         &self.board[y * self.width + x]
     }
@@ -276,6 +866,31 @@ impl Game {
         height: usize,
         num_mines: usize,
         first_click_policy: FirstClickPolicy,
+    ) -> Self {
+        let rng = StdRng::from_rng(&mut rand::rng());
+        Self::with_rng(width, height, num_mines, first_click_policy, rng)
+    }
+
+    /// Like `new`, but mines are placed with an RNG seeded from `seed`, so the
+    /// same seed and first click always give the same board.
+    pub fn with_seed(
+        width: usize,
+        height: usize,
+        num_mines: usize,
+        first_click_policy: FirstClickPolicy,
+        seed: u64,
+    ) -> Self {
+        let rng = StdRng::seed_from_u64(seed);
+        Self::with_rng(width, height, num_mines, first_click_policy, rng)
+    }
+
+    /// Like `new`, but mines are placed using the given RNG.
+    pub fn with_rng(
+        width: usize,
+        height: usize,
+        num_mines: usize,
+        first_click_policy: FirstClickPolicy,
+        rng: StdRng,
     ) -> Self {
         let board = vec![
             Cell {
@@ -295,41 +910,110 @@ impl Game {
             start_time: None,
             final_time: None,
             first_click_policy,
+            topology: Topology::Flat,
+            neighbourhood: Neighbourhood::Moore,
+            depth: 1,
+            rng,
+            revealed: 0,
+            mines_known: true,
+            history: Vec::new(),
+            future: Vec::new(),
+            log: Vec::new(),
+            assisted: false,
+            no_guess_failed: false,
+        }
+    }
+
+    /// The most cells `place_mines` keeps free of mines for the first click's
+    /// policy: the first click and its neighbours for a 0, just the first
+    /// click for a safe cell, none if it can hit a mine.
+    fn first_click_free_cells(&self) -> usize {
+        let neighbours = match self.depth {
+            1 => self.neighbourhood.offsets().len(),
+            _ => self.neighbourhood.offsets_3d().len(),
+        };
+        match self.first_click_policy {
+            FirstClickPolicy::GuaranteedZero | FirstClickPolicy::NoGuess => neighbours + 1,
+            FirstClickPolicy::GuaranteedSafe => 1,
+            FirstClickPolicy::Unprotected => 0,
         }
     }
 
     fn place_mines(&mut self, first_x: usize, first_y: usize) {
-        let mut rng = rand::rng();
-        let mut possible_positions: Vec<(usize, usize)> = (0..self.height)
+        let mut possible_positions: Vec<(usize, usize)> = (0..self.rows())
             .flat_map(|y| (0..self.width).map(move |x| (x, y)))
             .collect();
 
-        let avoid_width = match self.first_click_policy {
-            FirstClickPolicy::GuaranteedZero => 1,
-            FirstClickPolicy::GuaranteedSafe => 0,
-            FirstClickPolicy::Unprotected => -1,
+        let avoid = match self.first_click_policy {
+            FirstClickPolicy::GuaranteedZero | FirstClickPolicy::NoGuess => {
+                let mut avoid = self.neighbours(first_x, first_y);
+                avoid.push((first_x, first_y));
+                avoid
+            }
+            FirstClickPolicy::GuaranteedSafe => vec![(first_x, first_y)],
+            FirstClickPolicy::Unprotected => Vec::new(),
         };
 
-        // Remove the 3x3 area around the first click
-        possible_positions.retain(|(x, y)| {
-            !(((*x as isize - first_x as isize).abs() <= avoid_width)
-                && ((*y as isize - first_y as isize).abs() <= avoid_width))
-        });
+        // Remove the first click and, for a 0, the cells around it
+        possible_positions.retain(|cell| !avoid.contains(cell));
 
-        // Shuffle the valid positions
         use rand::seq::SliceRandom;
-        possible_positions.shuffle(&mut rng);
+        let start = Instant::now();
+        self.no_guess_failed = false;
+        for attempt in 1..=MAX_NO_GUESS_ATTEMPTS {
+            for cell in self.board.iter_mut() {
+                cell.content = CellContent::Number(0);
+            }
+
+            // Shuffle the valid positions
+            possible_positions.shuffle(&mut self.rng);
+
+            // Take the required number of mines from the shuffled list
+            for (x, y) in possible_positions.iter().take(self.num_mines) {
+                self.get_cell_mut(*x, *y).content = CellContent::Mine;
+            }
 
-        // Take the required number of mines from the shuffled list
-        for (x, y) in possible_positions.iter().take(self.num_mines) {
-            self.get_cell_mut(*x, *y).content = CellContent::Mine;
+            self.calculate_numbers();
+
+            if !matches!(self.first_click_policy, FirstClickPolicy::NoGuess)
+                || self.is_solvable_without_guessing(first_x, first_y)
+            {
+                break;
+            }
+            if attempt == MAX_NO_GUESS_ATTEMPTS || start.elapsed() > NO_GUESS_TIME_LIMIT {
+                self.no_guess_failed = true;
+                break;
+            }
         }
+    }
 
-        self.calculate_numbers();
+    /// True if `FirstClickPolicy::NoGuess` found no board it could solve by
+    /// logic alone in time, and dealt one that may need a guess.
+    pub fn no_guess_failed(&self) -> bool {
+        self.no_guess_failed
+    }
+
+    /// Plays the current mine layout from the given first click, revealing
+    /// only cells that `get_deductions` proves safe. Returns true if that
+    /// wins the game, i.e. no guess is ever needed.
+    fn is_solvable_without_guessing(&self, first_x: usize, first_y: usize) -> bool {
+        let mut sim = self.clone();
+        sim.first_click = false;
+        sim.reveal(first_x, first_y);
+        while sim.state == GameState::Playing {
+            let deductions = sim.get_deductions();
+            if deductions.safe.is_empty() {
+                return false;
+            }
+            for i in deductions.safe {
+                sim.reveal(i % sim.width, i / sim.width);
+            }
+        }
+        sim.state == GameState::Won
     }
 
     fn calculate_numbers(&mut self) {
-        for y in 0..self.height {
+        for y in 0..self.rows() {
             for x in 0..self.width {
                 if self.get_cell(x, y).content != CellContent::Mine {
                     let n = self.count_adjacent_mines(x, y);
@@ -340,113 +1024,503 @@ impl Game {
     }
 
     fn count_adjacent_mines(&self, x: usize, y: usize) -> u8 {
-        let mut count = 0;
-        for dy in -1..=1 {
-            for dx in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-                let (nx, ny) = (x as isize + dx, y as isize + dy);
-                if nx >= 0
-                    && nx < self.width as isize
-                    && ny >= 0
-                    && ny < self.height as isize
-                    && self.get_cell(nx as usize, ny as usize).content == CellContent::Mine
-                {
-                    count += 1;
-                }
-            }
-        }
-        count
+        self.neighbours(x, y)
+            .into_iter()
+            .filter(|&(nx, ny)| self.get_cell(nx, ny).content == CellContent::Mine)
+            .count() as u8
     }
 
     // returns adjacent cell indices for unrevealed states
     fn get_adjacent_unrevealed(&self, x: usize, y: usize) -> Vec<usize> {
-        let mut adjacent = Vec::new();
-        for dy in -1..=1 {
-            for dx in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-                let (nx, ny) = (x as isize + dx, y as isize + dy);
-                if nx >= 0 && nx < self.width as isize && ny >= 0 && ny < self.height as isize {
-                    let idx = (ny * self.width as isize + nx) as usize;
-                    if self.board[idx].state != CellState::Revealed {
-                        adjacent.push(idx);
-                    }
-                }
-            }
-        }
-        adjacent
+        self.neighbours(x, y)
+            .into_iter()
+            .map(|(nx, ny)| ny * self.width + nx)
+            .filter(|&idx| self.board[idx].state != CellState::Revealed)
+            .collect()
     }
 
     fn count_adjacent_revealed(&self, i: usize) -> usize {
-        let x = i % self.width;
-        let y = i / self.width;
-        let mut n = 0;
-        for dy in -1..=1 {
-            for dx in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-                let (nx, ny) = (x as isize + dx, y as isize + dy);
-                if nx >= 0 && nx < self.width as isize && ny >= 0 && ny < self.height as isize {
-                    let idx = (ny * self.width as isize + nx) as usize;
-                    if self.board[idx].state == CellState::Revealed {
-                        n += 1
-                    }
-                }
-            }
-        }
-        n
+        self.neighbours(i % self.width, i / self.width)
+            .into_iter()
+            .filter(|&(nx, ny)| self.get_cell(nx, ny).state == CellState::Revealed)
+            .count()
     }
 
+    /// Reveals a covered cell (placing the mines on the first click), and
+    /// cascades if it is a 0. Recorded as one move in the undo history.
     pub fn reveal(&mut self, x: usize, y: usize) {
-        if x >= self.width || y >= self.height || self.get_cell(x, y).state != CellState::Covered {
+        self.log_event(LogEvent::Action(Action::Reveal(x, y)));
+        self.record(|game, changes| game.reveal_cell(x, y, changes));
+    }
+
+    /// Chords on a revealed number: if it has as many flagged neighbours as
+    /// its value, all its other covered neighbours are revealed as one move.
+    /// A wrongly placed flag means a mine is revealed and the game is lost.
+    pub fn chord(&mut self, x: usize, y: usize) {
+        self.log_event(LogEvent::Action(Action::Chord(x, y)));
+        self.record(|game, changes| game.chord_cell(x, y, changes));
+    }
+
+    /// Toggles a flag on a covered cell. Recorded in the undo history.
+    pub fn flag(&mut self, x: usize, y: usize) {
+        self.log_event(LogEvent::Action(Action::Flag(x, y)));
+        self.record(|game, changes| game.toggle_flag(x, y, changes));
+    }
+
+    /// Records a cursor move in the move log. The game itself has no
+    /// cursor, so front ends call this to make replays complete.
+    pub fn log_cursor(&mut self, x: usize, y: usize) {
+        self.log_event(LogEvent::Cursor(x, y));
+    }
+
+    fn log_event(&mut self, event: LogEvent) {
+        let time = self
+            .start_time
+            .map(|start| start.elapsed())
+            .unwrap_or_default();
+        self.log.push(LogEntry { time, event });
+    }
+
+    /// Everything the player has done so far, in order. Actions are logged
+    /// even if they had no effect (e.g. revealing a revealed cell).
+    pub fn move_log(&self) -> &[LogEntry] {
+        &self.log
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            state: self.state.clone(),
+            first_click: self.first_click,
+            revealed: self.revealed,
+            start_time: self.start_time,
+            final_time: self.final_time,
+        }
+    }
+
+    fn set_status(&mut self, status: Status) {
+        self.state = status.state;
+        self.first_click = status.first_click;
+        self.revealed = status.revealed;
+        self.start_time = status.start_time;
+        self.final_time = status.final_time;
+    }
+
+    /// Runs an action, which lists the cells it changes, and if it changed
+    /// anything pushes it onto the undo history as a single move. A new move
+    /// clears the redo history.
+    fn record(&mut self, action: impl FnOnce(&mut Self, &mut Vec<Change>)) {
+        let before = self.status();
+        let mut changes = Vec::new();
+        action(self, &mut changes);
+        let after = self.status();
+
+        if changes.is_empty() && before == after {
+            return;
+        }
+        self.history.push(Move {
+            changes,
+            before,
+            after,
+        });
+        self.future.clear();
+    }
+
+    /// Takes back the last move, including a losing reveal. Returns false if
+    /// there is nothing to undo. Marks the game as assisted.
+    pub fn undo(&mut self) -> bool {
+        let Some(m) = self.history.pop() else {
+            return false;
+        };
+        for &(i, old, _) in m.changes.iter().rev() {
+            self.board[i] = old;
+        }
+        self.set_status(m.before.clone());
+        self.future.push(m);
+        self.assisted = true;
+        self.log_event(LogEvent::Undo);
+        true
+    }
+
+    /// Replays the last undone move. Returns false if there is nothing to
+    /// redo. Marks the game as assisted.
+    pub fn redo(&mut self) -> bool {
+        let Some(m) = self.future.pop() else {
+            return false;
+        };
+        for &(i, _, new) in &m.changes {
+            self.board[i] = new;
+        }
+        self.set_status(m.after.clone());
+        self.history.push(m);
+        self.assisted = true;
+        self.log_event(LogEvent::Redo);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.future.is_empty()
+    }
+
+    fn reveal_cell(&mut self, x: usize, y: usize, changes: &mut Vec<Change>) {
+        if x >= self.width
+            || y >= self.rows()
+            || self.get_cell(x, y).state != CellState::Covered
+            || !self.mines_known
+        {
             return;
         }
 
         if self.first_click {
+            // Placing the mines can change any cell, but only once a game
+            let board_before = self.board.clone();
             self.place_mines(x, y);
+            changes.extend(
+                board_before
+                    .into_iter()
+                    .zip(&self.board)
+                    .enumerate()
+                    .filter(|(_, (old, new))| old != *new)
+                    .map(|(i, (old, new))| (i, old, *new)),
+            );
             self.first_click = false;
             self.start_time = Some(Instant::now());
         }
 
-        self.get_cell_mut(x, y).state = CellState::Revealed;
-        match self.get_cell(x, y).content {
-            CellContent::Mine => {
-                self.state = GameState::Lost;
+        self.flood_reveal(x, y, changes);
+        self.check_win_condition();
+    }
+
+    /// Reveals the cell and, if it is a 0, cascades into its neighbours.
+    /// Uses an explicit work stack rather than recursion, so large openings
+    /// can't overflow the call stack. Does not check for a win.
+    fn flood_reveal(&mut self, x: usize, y: usize, changes: &mut Vec<Change>) {
+        let mut stack = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            let before = *self.get_cell(x, y);
+            if before.state != CellState::Covered {
+                continue;
+            }
+            self.get_cell_mut(x, y).state = CellState::Revealed;
+            self.revealed += 1;
+            if before.content == CellContent::Mine {
                 self.get_cell_mut(x, y).content = CellContent::Explosion;
-                if let Some(start) = self.start_time {
-                    self.final_time = Some(start.elapsed());
-                }
             }
-            CellContent::Number(0) => {
-                for dy in -1..=1 {
-                    for dx in -1..=1 {
-                        let (nx, ny) = (x as isize + dx, y as isize + dy);
-                        if nx >= 0
-                            && nx < self.width as isize
-                            && ny >= 0
-                            && ny < self.height as isize
-                        {
-                            self.reveal(nx as usize, ny as usize);
+            changes.push((y * self.width + x, before, *self.get_cell(x, y)));
+            match before.content {
+                CellContent::Mine => {
+                    self.state = GameState::Lost;
+                    if let Some(start) = self.start_time {
+                        self.final_time = Some(start.elapsed());
+                    }
+                }
+                CellContent::Number(0) => {
+                    for (nx, ny) in self.neighbours(x, y) {
+                        if self.get_cell(nx, ny).state == CellState::Covered {
+                            stack.push((nx, ny));
                         }
                     }
                 }
+                _ => {}
+            }
+        }
+    }
+
+    fn chord_cell(&mut self, x: usize, y: usize, changes: &mut Vec<Change>) {
+        if x >= self.width
+            || y >= self.rows()
+            || self.state != GameState::Playing
+            || !self.mines_known
+        {
+            return;
+        }
+        let n = match *self.get_cell(x, y) {
+            Cell {
+                content: CellContent::Number(n),
+                state: CellState::Revealed,
+            } if n > 0 => n as usize,
+            _ => return,
+        };
+
+        let neighbours = self.neighbours(x, y);
+        let flagged = neighbours
+            .iter()
+            .filter(|&&(nx, ny)| self.get_cell(nx, ny).state == CellState::Flagged)
+            .count();
+        if flagged != n {
+            return;
+        }
+        for (nx, ny) in neighbours {
+            if self.state != GameState::Playing {
+                break;
             }
-            _ => {}
+            self.flood_reveal(nx, ny, changes);
         }
         self.check_win_condition();
     }
 
-    pub fn flag(&mut self, x: usize, y: usize) {
-        if x < self.width && y < self.height && self.get_cell(x, y).state != CellState::Revealed {
-            self.get_cell_mut(x, y).state = match self.get_cell(x, y).state {
+    /// Returns the coordinates of the cells adjacent to (x, y) - the 8
+    /// around it (26 on a 3D board), unless the game has another
+    /// `Neighbourhood`. On a torus they wrap around the edges; if a small
+    /// board then reaches the same cell twice, it is listed once.
+    pub fn neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let (width, height, depth) = (
+            self.width as isize,
+            self.height as isize,
+            self.depth as isize,
+        );
+        let (z, y_in_layer) = (y / self.height, y % self.height);
+        let mut neighbours = Vec::with_capacity(8);
+        let mut add = |(dx, dy, dz): (isize, isize, isize)| {
+            let (nx, ny) = match self.neighbourhood {
+                Neighbourhood::Hex => {
+                    let (q, r) = hex::to_axial(x as isize, y_in_layer as isize);
+                    hex::from_axial(q + dx, r + dy)
+                }
+                _ => (x as isize + dx, y_in_layer as isize + dy),
+            };
+            let nz = z as isize + dz;
+            let (nx, ny, nz) = match self.topology {
+                Topology::Flat => {
+                    if nx < 0 || nx >= width || ny < 0 || ny >= height || nz < 0 || nz >= depth {
+                        return;
+                    }
+                    (nx, ny, nz)
+                }
+                Topology::Torus => (
+                    nx.rem_euclid(width),
+                    ny.rem_euclid(height),
+                    nz.rem_euclid(depth),
+                ),
+            };
+            let cell = (nx as usize, (nz * height + ny) as usize);
+            if self.topology == Topology::Torus && (cell == (x, y) || neighbours.contains(&cell)) {
+                return;
+            }
+            neighbours.push(cell);
+        };
+        if self.depth == 1 {
+            for &(dx, dy) in self.neighbourhood.offsets() {
+                add((dx, dy, 0));
+            }
+        } else {
+            for &offset in self.neighbourhood.offsets_3d() {
+                add(offset);
+            }
+        }
+        neighbours
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Sets how the edges join up, e.g. `Topology::Torus` for a board whose
+    /// neighbours wrap around. Numbers are worked out again if the mines are
+    /// already placed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the board is a hex torus with an odd number of rows, see
+    /// `check_shape`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use minesweeper_rs::{Topology, game::Game};
+    ///
+    /// let game = Game::from_text("*...\n....\n....\n....")
+    ///     .unwrap()
+    ///     .with_topology(Topology::Torus);
+    /// assert_eq!(game.neighbours(3, 3).len(), 8);
+    /// assert!(game.neighbours(3, 3).contains(&(0, 0)));
+    /// ```
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self.assert_shape();
+        if self.mines_placed() {
+            self.calculate_numbers();
+        }
+        self
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    /// True if row `y` is drawn half a cell to the right, as the odd rows
+    /// of a hex board are.
+    pub fn is_shifted(&self, y: usize) -> bool {
+        self.neighbourhood == Neighbourhood::Hex && y % self.height % 2 == 1
+    }
+
+    /// Number of layers: 1 for the usual flat board, more for a 3D one.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Rows of every layer together, one layer after another: the `y` of
+    /// the cell methods runs over these, so cell (x, y, z) is row
+    /// `z * height + y`. The same as `height` on a flat board.
+    pub fn rows(&self) -> usize {
+        self.height * self.depth
+    }
+
+    /// The column, row within its layer, and layer of the cell at (x, y).
+    pub fn position(&self, x: usize, y: usize) -> (usize, usize, usize) {
+        (x, y % self.height, y / self.height)
+    }
+
+    /// The cell at (x, y) as shown to the player: "(x,y)", or "(x,y,z)"
+    /// with the row within the layer on a 3D board.
+    pub fn cell_name(&self, x: usize, y: usize) -> String {
+        match self.position(x, y) {
+            (x, y, _) if self.depth == 1 => format!("({x},{y})"),
+            (x, y, z) => format!("({x},{y},{z})"),
+        }
+    }
+
+    /// Makes a new board 3D: `depth` layers of `width` x `height` cells,
+    /// stacked one after another in the rows (see `rows`). The mines are
+    /// placed in all of them on the first click. Boards with their mines
+    /// already placed get their layers from `from_text` instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `depth` is 0, if the mines are already placed, or if the
+    /// board is a hex torus with an odd number of rows in each layer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use minesweeper_rs::{FirstClickPolicy, game::Game};
+    ///
+    /// // A 3x3x3 cube
+    /// let game = Game::new(3, 3, 1, FirstClickPolicy::GuaranteedSafe).with_depth(3);
+    /// assert_eq!((game.height, game.rows()), (3, 9));
+    /// assert_eq!(game.neighbours(1, 4).len(), 26);
+    /// ```
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        assert!(depth > 0, "a board needs at least one layer");
+        assert!(
+            self.first_click,
+            "layers can't be added once the mines are placed"
+        );
+        let covered = Cell {
+            content: CellContent::Number(0),
+            state: CellState::Covered,
+        };
+        self.board = vec![covered; self.width * self.height * depth];
+        self.depth = depth;
+        self.assert_shape();
+        self
+    }
+
+    /// Sets which cells are neighbours, e.g. `Neighbourhood::Knight` for
+    /// numbers that count the mines a knight's move away. Numbers are
+    /// worked out again if the mines are already placed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the board is a hex torus with an odd number of rows, see
+    /// `check_shape`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use minesweeper_rs::{Neighbourhood, game::Game};
+    ///
+    /// let game = Game::from_text("*..\n...\n...")
+    ///     .unwrap()
+    ///     .with_neighbourhood(Neighbourhood::Knight);
+    /// assert_eq!(game.neighbours(0, 0), vec![(2, 1), (1, 2)]);
+    /// ```
+    pub fn with_neighbourhood(mut self, neighbourhood: Neighbourhood) -> Self {
+        self.neighbourhood = neighbourhood;
+        self.assert_shape();
+        if self.mines_placed() {
+            self.calculate_numbers();
+        }
+        self
+    }
+
+    /// Checks that a board with `rows` rows in each layer can have this
+    /// topology and neighbourhood. A hex torus needs an even number, or the
+    /// shifted rows don't line up across the top and bottom edges and a
+    /// cell can list a neighbour that doesn't list it back.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OddHexTorus` for a hex torus with an odd number of rows.
+    pub fn check_shape(
+        topology: Topology,
+        neighbourhood: Neighbourhood,
+        rows: usize,
+    ) -> Result<(), ParseGameError> {
+        if topology == Topology::Torus
+            && neighbourhood == Neighbourhood::Hex
+            && !rows.is_multiple_of(2)
+        {
+            return Err(ParseGameError::OddHexTorus { rows });
+        }
+        Ok(())
+    }
+
+    fn assert_shape(&self) {
+        if let Err(err) = Game::check_shape(self.topology, self.neighbourhood, self.height) {
+            panic!("{err}");
+        }
+    }
+
+    /// True if (x, y) holds a mine, exploded or not.
+    pub fn is_mine(&self, x: usize, y: usize) -> bool {
+        matches!(
+            self.get_cell(x, y).content,
+            CellContent::Mine | CellContent::Explosion
+        )
+    }
+
+    /// False until the first click has placed the mines, and always false
+    /// for player-view positions.
+    pub fn mines_placed(&self) -> bool {
+        !self.first_click && self.mines_known
+    }
+
+    /// Performs a logged event, e.g. when playing back a replay.
+    pub fn apply_log_event(&mut self, event: LogEvent) {
+        match event {
+            LogEvent::Cursor(x, y) => self.log_cursor(x, y),
+            LogEvent::Action(action) => self.apply(action),
+            LogEvent::Undo => {
+                self.undo();
+            }
+            LogEvent::Redo => {
+                self.redo();
+            }
+        }
+    }
+
+    /// Performs a player action.
+    pub fn apply(&mut self, action: Action) {
+        match action {
+            Action::Reveal(x, y) => self.reveal(x, y),
+            Action::Flag(x, y) => self.flag(x, y),
+            Action::Chord(x, y) => self.chord(x, y),
+        }
+    }
+
+    fn toggle_flag(&mut self, x: usize, y: usize, changes: &mut Vec<Change>) {
+        if x < self.width && y < self.rows() && self.get_cell(x, y).state != CellState::Revealed {
+            let before = *self.get_cell(x, y);
+            self.get_cell_mut(x, y).state = match before.state {
                 CellState::Covered => CellState::Flagged,
                 CellState::Flagged => CellState::Covered,
-                _ => self.get_cell(x, y).state,
+                _ => before.state,
             };
+            changes.push((y * self.width + x, before, *self.get_cell(x, y)));
         }
     }
 
@@ -455,13 +1529,16 @@ impl Game {
     }
 
     fn check_win_condition(&mut self) {
-        let non_mine_cells = self.width * self.height - self.num_mines;
-        if self.count(CellState::Revealed) == non_mine_cells {
+        if self.state != GameState::Playing {
+            return; // an exploded mine counts as revealed - don't turn a loss into a win
+        }
+        let non_mine_cells = self.board.len() - self.num_mines;
+        if self.revealed == non_mine_cells {
             self.state = GameState::Won;
-            if self.final_time.is_none() {
-                if let Some(start) = self.start_time {
-                    self.final_time = Some(start.elapsed());
-                }
+            if self.final_time.is_none()
+                && let Some(start) = self.start_time
+            {
+                self.final_time = Some(start.elapsed());
             }
         }
     }
@@ -477,6 +1554,7 @@ impl Game {
 
     pub fn get_sea_of_unknown(&self) -> Vec<usize> {
         (0..self.board.len())
+            .filter(|&i| self.board[i].state != CellState::Revealed)
             .map(|i| (i, self.count_adjacent_revealed(i) == 0))
             .filter(|(_, b)| *b)
             .map(|(i, _)| i)
@@ -496,7 +1574,7 @@ impl Game {
         let global_constraint = Constraint::new(unknown_indices, self.num_mines as f64);
 
         // Add number constraints from unrevealed neighbours
-        for y in 0..self.height {
+        for y in 0..self.rows() {
             for x in 0..self.width {
                 if let Cell {
                     content: CellContent::Number(n),
@@ -512,12 +1590,24 @@ impl Game {
             }
         }
         sea_of_unknown.sort_unstable();
-        // Convert the HashSet into a Vec for the return type
-        (
-            global_constraint,
-            constraints_set.into_iter().collect(),
-            sea_of_unknown,
-        )
+        // Convert the HashSet into a Vec for the return type - sorted, so that
+        // results (and seeded benchmark runs) don't depend on hash order.
+        let mut local_constraints: Vec<Constraint> = constraints_set.into_iter().collect();
+        local_constraints.sort_unstable_by(|a, b| a.cells.cmp(&b.cells));
+        (global_constraint, local_constraints, sea_of_unknown)
+    }
+
+    /// Returns the covered cells that are certainly safe and certainly mines,
+    /// found by single-point and pairwise-difference reasoning on the
+    /// constraints from `get_constraints` (no probabilities involved).
+    /// Flags are not trusted - flagged cells are treated as covered.
+    pub fn get_deductions(&self) -> Deductions {
+        if self.state != GameState::Playing {
+            return Deductions::default();
+        }
+        let (global_constraint, mut local_constraints, _sea_of_unknown) = self.get_constraints();
+        local_constraints.push(global_constraint);
+        solver::deduce(&local_constraints)
     }
 
     pub fn get_bomb_prob(&self, cell_x: usize, cell_y: usize) -> f64 {
@@ -530,7 +1620,7 @@ impl Game {
     }
 
     pub fn calculate_all_bomb_probs(&self) -> Vec<f64> {
-        let n_cells = self.width * self.height;
+        let n_cells = self.board.len();
         if self.state != GameState::Playing {
             return vec![0.0; n_cells];
         }
@@ -558,12 +1648,26 @@ impl Game {
         solver::solve_iterative_scaling(&mut p, &mut q, &local_constraints, 100);
         p
     }
+
+    /// Like `calculate_all_bomb_probs`, but uses exact frontier enumeration
+    /// instead of iterative scaling. Falls back to iterative scaling if the
+    /// constraints are inconsistent (e.g. a hand-made position).
+    pub fn calculate_exact_bomb_probs(&self) -> Vec<f64> {
+        let n_cells = self.board.len();
+        if self.state != GameState::Playing {
+            return vec![0.0; n_cells];
+        }
+
+        let (global_constraint, local_constraints, _sea_of_unknown) = self.get_constraints();
+        solver::solve_exact(&global_constraint, &local_constraints, n_cells)
+            .unwrap_or_else(|| self.calculate_all_bomb_probs())
+    }
 }
 
 pub fn display_probs(game: &Game, probs: &[f64]) {
-    assert!(probs.len() == game.height * game.width);
+    assert!(probs.len() == game.rows() * game.width);
     println!("\nProbability map:");
-    for row in 0..game.height {
+    for row in 0..game.rows() {
         for col in 0..game.width {
             let idx = row * game.width + col;
             if game.get_cell(col, row).state == CellState::Revealed {
@@ -575,3 +1679,377 @@ pub fn display_probs(game: &Game, probs: &[f64]) {
         println!();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deductions_after_reveal() {
+        // Revealing the top-left corner opens everything up to a "1 2 1"
+        // column next to the covered right-hand column.
+        let mut game = Game::from_text(
+            "
+            ...*
+            ....
+            ...*
+            ",
+        )
+        .unwrap();
+        game.reveal(0, 0);
+        let d = game.get_deductions();
+        assert_eq!(d.mines, vec![3, 11]);
+        assert_eq!(d.safe, vec![7]);
+    }
+
+    #[test]
+    fn test_chord() {
+        let layout = "
+            *..
+            ...
+            ..*
+            ";
+
+        // Correct flag: chording the 1 at (1,0) reveals its other neighbours
+        let mut game = Game::from_text(layout).unwrap();
+        game.reveal(1, 0);
+        game.chord(1, 0); // no flag yet - nothing happens
+        assert_eq!(game.count(CellState::Revealed), 1);
+        game.flag(0, 0);
+        game.chord(1, 0);
+        assert_eq!(game.count(CellState::Revealed), 5);
+        assert_eq!(game.state, GameState::Playing);
+
+        // Wrong flag: chording reveals the mine and loses
+        let mut game = Game::from_text(layout).unwrap();
+        game.reveal(1, 1);
+        game.flag(0, 0);
+        game.chord(1, 1); // the centre is a 2 - one flag is not enough
+        assert_eq!(game.state, GameState::Playing);
+        game.flag(0, 1);
+        game.chord(1, 1);
+        assert_eq!(game.state, GameState::Lost);
+        assert_eq!(game.get_cell(2, 2).content, CellContent::Explosion);
+    }
+
+    #[test]
+    fn test_huge_opening_does_not_overflow() {
+        let mut game = Game::with_seed(1000, 1000, 5, FirstClickPolicy::GuaranteedZero, 3);
+        game.reveal(500, 500);
+        assert_eq!(game.revealed, game.count(CellState::Revealed));
+        assert!(game.revealed > 900_000);
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut game = Game::with_seed(9, 9, 10, FirstClickPolicy::GuaranteedSafe, 5);
+        let unstarted = Game::from_save_text(&game.to_save_text()).unwrap();
+        assert!(unstarted.first_click && unstarted.start_time.is_none());
+
+        game.reveal(4, 4);
+        let mine = game
+            .board
+            .iter()
+            .position(|c| c.content == CellContent::Mine)
+            .unwrap();
+        game.flag(mine % 9, mine / 9);
+
+        let text = game.to_save_text();
+        let loaded = Game::from_save_text(&text).unwrap();
+        assert_eq!(loaded.to_save_text().lines().count(), text.lines().count());
+        assert_eq!(loaded.num_mines, 10);
+        assert_eq!(loaded.revealed, game.revealed);
+        assert_eq!(loaded.state, GameState::Playing);
+        assert!(!loaded.first_click);
+        for (a, b) in loaded.board.iter().zip(&game.board) {
+            assert_eq!((a.content, a.state), (b.content, b.state));
+        }
+
+        // A lost game keeps its explosion and final time
+        let mut lost = Game::from_text("*.").unwrap();
+        lost.reveal(0, 0);
+        let loaded = Game::from_save_text(&lost.to_save_text()).unwrap();
+        assert_eq!(loaded.state, GameState::Lost);
+        assert_eq!(loaded.get_cell(0, 0).content, CellContent::Explosion);
+        assert_eq!(loaded.get_cell(1, 0).content, CellContent::Number(1));
+        assert!(loaded.final_time.is_some());
+    }
+
+    #[test]
+    fn test_save_rejects_bad_input() {
+        let text = "minesweeper-rs save 1\nmines 1\npolicy unprotected\nfirst_click false\nstate playing\nelapsed_ms 0\nboard\n. . *\n";
+        assert!(Game::from_save_text(text).is_ok());
+        assert!(matches!(
+            Game::from_save_text(&text.replace("mines 1", "mines 2")),
+            Err(ParseGameError::MineCountMismatch { .. })
+        ));
+        assert!(matches!(
+            Game::from_save_text(&text.replace("state playing\n", "")),
+            Err(ParseGameError::MissingField("state"))
+        ));
+        assert!(matches!(
+            Game::from_save_text(&text.replace(". . *", ". ? *")),
+            Err(ParseGameError::InvalidCell { character: '?', .. })
+        ));
+
+        // Before the first click, the mines must fit on the board
+        let unstarted = text
+            .replace("first_click false", "first_click true")
+            .replace(". . *", ". . .");
+        assert!(Game::from_save_text(&unstarted.replace("mines 1", "mines 3")).is_ok());
+        assert!(matches!(
+            Game::from_save_text(&unstarted.replace("mines 1", "mines 10")),
+            Err(ParseGameError::InvalidField { .. })
+        ));
+        let guaranteed_zero = unstarted.replace("unprotected", "guaranteed-zero");
+        assert!(Game::from_save_text(&guaranteed_zero.replace("mines 1", "mines 3")).is_err());
+    }
+
+    #[test]
+    fn test_player_view_analysis() {
+        let mut game = Game::from_player_view(
+            "
+            1 1 # #
+            1 # # #
+            F # # #
+            ",
+            3,
+        )
+        .unwrap();
+        assert!(game.is_analysis());
+        assert_eq!((game.width, game.height), (4, 3));
+
+        let d = game.get_deductions();
+        assert_eq!(d.mines, vec![5]);
+        assert!(d.safe.contains(&2) && d.safe.contains(&6) && d.safe.contains(&8));
+
+        let exact = game.calculate_exact_bomb_probs();
+        assert_eq!(exact[5], 1.0);
+        assert_eq!(exact[2], 0.0);
+        let approx = game.calculate_all_bomb_probs();
+        assert!(approx[5] > 0.99);
+
+        // The layout is unknown, so the position can't be played
+        game.reveal(3, 0);
+        assert_eq!(game.get_cell(3, 0).state, CellState::Covered);
+
+        assert!(matches!(
+            Game::from_player_view("1 # / 1 ?", 1),
+            Err(ParseGameError::InvalidCell { character: '?', .. })
+        ));
+        // Numbers that contradict each other, and more mines than cells
+        assert!(matches!(
+            Game::from_player_view("1 0 / # .", 1),
+            Err(ParseGameError::ImpossiblePosition)
+        ));
+        assert!(matches!(
+            Game::from_player_view("1 # / 1 #", 3),
+            Err(ParseGameError::ImpossiblePosition)
+        ));
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut game = Game::from_text(
+            "
+            *..
+            ...
+            ..*
+            ",
+        )
+        .unwrap();
+        assert!(!game.undo());
+
+        game.flag(0, 0);
+        game.reveal(1, 0);
+        // A move keeps only the cells it changed
+        assert_eq!(game.history.last().unwrap().changes.len(), 1);
+        game.reveal(2, 2); // boom
+        assert_eq!(game.state, GameState::Lost);
+        assert!(!game.assisted);
+
+        // Undoing the losing reveal restores the mine and the Playing state
+        assert!(game.undo());
+        assert_eq!(game.state, GameState::Playing);
+        assert_eq!(game.get_cell(2, 2).content, CellContent::Mine);
+        assert_eq!(game.get_cell(2, 2).state, CellState::Covered);
+        assert!(game.final_time.is_none());
+        assert!(game.assisted);
+
+        assert!(game.undo());
+        assert!(game.undo());
+        assert_eq!(game.count(CellState::Covered), 9);
+        assert!(!game.undo());
+
+        assert!(game.redo());
+        assert!(game.redo());
+        assert_eq!(game.get_cell(0, 0).state, CellState::Flagged);
+        assert_eq!(game.revealed, 1);
+
+        // A new move drops the redo history
+        game.reveal(2, 0);
+        assert!(!game.redo());
+        assert_eq!(game.revealed, game.count(CellState::Revealed));
+    }
+
+    #[test]
+    fn test_undo_first_click() {
+        let mut game = Game::with_seed(9, 9, 10, FirstClickPolicy::GuaranteedZero, 4);
+        game.reveal(4, 4);
+        let placed = game.to_save_text();
+        game.undo();
+        assert!(game.first_click && game.start_time.is_none());
+        game.redo();
+        // Redo brings back the same mines, not a new layout
+        assert_eq!(game.to_save_text().lines().last(), placed.lines().last());
+    }
+
+    #[test]
+    fn test_seeded_games_are_reproducible() {
+        let mines = |seed| {
+            let mut game = Game::with_seed(16, 16, 40, FirstClickPolicy::GuaranteedZero, seed);
+            game.reveal(3, 4);
+            game.board
+                .iter()
+                .map(|c| c.content == CellContent::Mine)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(mines(42), mines(42));
+        assert_ne!(mines(42), mines(43));
+    }
+
+    #[test]
+    fn test_torus() {
+        let layout = "*...\n....\n....\n....";
+        let flat = Game::from_text(layout).unwrap();
+        assert_eq!(flat.get_cell(3, 3).content, CellContent::Number(0));
+
+        // The far corner touches the mine across both edges, and the zeros
+        // along the middle row and column open the whole board
+        let mut game = Game::from_text(layout)
+            .unwrap()
+            .with_topology(Topology::Torus);
+        assert_eq!(game.get_cell(3, 3).content, CellContent::Number(1));
+        game.reveal(2, 2);
+        assert_eq!(game.state, GameState::Won);
+        let loaded = Game::from_save_text(&game.to_save_text()).unwrap();
+        assert_eq!(loaded.topology(), Topology::Torus);
+        assert_eq!(loaded.get_cell(3, 3).content, CellContent::Number(1));
+
+        // A first click in the corner keeps the wrapped-around cells clear
+        for seed in 0..20 {
+            let mut game = Game::with_seed(5, 5, 16, FirstClickPolicy::GuaranteedZero, seed)
+                .with_topology(Topology::Torus);
+            game.reveal(0, 0);
+            assert_eq!(game.get_cell(0, 0).content, CellContent::Number(0));
+            assert!(!game.is_mine(4, 4));
+        }
+
+        // Two cells across: left and right are the same neighbour
+        let game = Game::from_text("..\n..")
+            .unwrap()
+            .with_topology(Topology::Torus);
+        assert_eq!(game.neighbours(0, 0).len(), 3);
+    }
+
+    #[test]
+    fn test_neighbourhoods() {
+        // On a 3x3 board the knight's moves link the outer cells in a ring
+        // and leave the centre on its own
+        let mut game = Game::from_text("*..\n...\n...")
+            .unwrap()
+            .with_neighbourhood(Neighbourhood::Knight);
+        assert_eq!(game.get_cell(2, 1).content, CellContent::Number(1));
+        assert_eq!(game.get_cell(1, 1).content, CellContent::Number(0));
+        game.reveal(2, 2);
+        assert_eq!(game.count(CellState::Revealed), 7);
+        assert_eq!(game.get_deductions().mines, vec![0]);
+        game.reveal(1, 1);
+        assert_eq!(game.state, GameState::Won);
+
+        // A 0 only opens up, down, left and right
+        let mut game = Game::from_text("*..\n...\n...")
+            .unwrap()
+            .with_neighbourhood(Neighbourhood::Orthogonal);
+        assert_eq!(game.get_cell(1, 1).content, CellContent::Number(0));
+        game.reveal(2, 2);
+        assert_eq!(game.count(CellState::Revealed), 8);
+
+        // Numbers above 8, which survive a save
+        let mut game = Game::from_text("*****\n*****\n*....\n.....\n.....")
+            .unwrap()
+            .with_neighbourhood(Neighbourhood::Radius2);
+        game.reveal(2, 2);
+        assert_eq!(game.get_cell(2, 2).content, CellContent::Number(11));
+        let loaded = Game::from_save_text(&game.to_save_text()).unwrap();
+        assert_eq!(loaded.neighbourhood(), Neighbourhood::Radius2);
+        assert_eq!(loaded.get_cell(2, 2).content, CellContent::Number(11));
+    }
+
+    #[test]
+    fn test_layers() {
+        // Two 3x3 layers with a mine in a corner of the top one: the cell
+        // below it counts it, and a 0 in the bottom layer opens the rest of
+        // both but that cell, whose neighbours are all numbers
+        let mut game = Game::from_text("*..\n...\n...\n\n...\n...\n...").unwrap();
+        assert_eq!((game.height, game.depth(), game.rows()), (3, 2, 6));
+        assert_eq!(game.position(1, 4), (1, 1, 1));
+        assert_eq!(game.cell_name(1, 4), "(1,1,1)");
+        assert_eq!(game.get_cell(0, 3).content, CellContent::Number(1));
+        assert_eq!(game.get_cell(1, 4).content, CellContent::Number(1));
+        assert_eq!(game.get_cell(2, 5).content, CellContent::Number(0));
+        // A corner touches 3 cells in its own layer and 4 in the next
+        assert_eq!(game.neighbours(2, 2).len(), 7);
+        game.reveal(2, 5);
+        assert_eq!(game.count(CellState::Revealed), 16);
+        game.reveal(0, 3);
+        assert_eq!(game.state, GameState::Won);
+
+        let loaded = Game::from_save_text(&game.to_save_text()).unwrap();
+        assert_eq!((loaded.height, loaded.depth()), (3, 2));
+        assert_eq!(loaded.to_save_text(), game.to_save_text());
+        assert!(Game::from_save_text(&game.to_save_text().replace("depth 2", "depth 4")).is_err());
+        assert!(matches!(
+            Game::from_text("*..\n...\n\n..."),
+            Err(ParseGameError::InconsistentLayerHeight {
+                expected: 2,
+                actual: 1,
+                layer_index: 1,
+            })
+        ));
+
+        // A new board gets its layers before the mines are placed in them
+        let mut game = Game::with_seed(4, 3, 5, FirstClickPolicy::GuaranteedSafe, 1).with_depth(2);
+        assert_eq!((game.height, game.rows()), (3, 6));
+        game.reveal(3, 5);
+        let mines = (0..game.rows())
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .filter(|&(x, y)| game.is_mine(x, y))
+            .count();
+        assert_eq!(mines, 5);
+        assert!(!game.is_mine(3, 5));
+    }
+
+    #[test]
+    fn test_no_guess_board_is_solvable_by_logic() {
+        let mut game = Game::with_seed(16, 16, 40, FirstClickPolicy::NoGuess, 1);
+        game.reveal(8, 8);
+        assert!(!game.no_guess_failed());
+        while game.state == GameState::Playing {
+            let safe = game.get_deductions().safe;
+            assert!(!safe.is_empty(), "needed a guess:\n{game}");
+            for i in safe {
+                game.reveal(i % game.width, i / game.width);
+            }
+        }
+        assert_eq!(game.state, GameState::Won);
+    }
+
+    #[test]
+    fn test_no_guess_gives_up_on_dense_boards() {
+        let mut game = Game::with_seed(9, 9, 50, FirstClickPolicy::NoGuess, 1);
+        game.reveal(4, 4);
+        assert!(game.no_guess_failed());
+        assert_eq!(game.state, GameState::Playing);
+    }
+}
@@ -0,0 +1,81 @@
+// Hexagonal boards. Cells are stored row by row like on any other board,
+// with every odd row shifted half a cell to the right (the "odd-r"
+// layout), so (x, y) is still a column and a row:
+//
+//     0,0 1,0 2,0
+//       0,1 1,1 2,1
+//     0,2 1,2 2,2
+//
+// Which cells touch depends on the row, so neighbours are found in axial
+// coordinates (q, r) instead, where the six directions are the same for
+// every cell. On a torus the height must be even for the shifted rows to
+// line up across the top and bottom edges (see `Game::check_shape`).
+
+/// The (dq, dr) of the six neighbours in axial coordinates: two above, one
+/// either side and two below.
+pub const DIRECTIONS: [(isize, isize); 6] = [(0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+
+/// Axial coordinates of the cell in column `x` of row `y`.
+pub fn to_axial(x: isize, y: isize) -> (isize, isize) {
+    (x - y.div_euclid(2), y)
+}
+
+/// Column and row of the cell at axial coordinates (q, r).
+pub fn from_axial(q: isize, r: isize) -> (isize, isize) {
+    (q + r.div_euclid(2), r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{CellContent, Game, ParseGameError};
+    use crate::{Neighbourhood, Topology};
+
+    #[test]
+    fn test_hex_neighbours() {
+        assert_eq!(from_axial(to_axial(3, -5).0, -5), (3, -5));
+        // Cells in an odd row touch the row above at their own column and
+        // the one to the right
+        let (q, r) = to_axial(1, 1);
+        assert_eq!(from_axial(q, r - 1), (1, 0));
+        assert_eq!(from_axial(q + 1, r - 1), (2, 0));
+
+        let game = Game::from_text("*...\n....\n....\n....")
+            .unwrap()
+            .with_neighbourhood(Neighbourhood::Hex);
+        assert_eq!(game.neighbours(0, 0), vec![(1, 0), (0, 1)]);
+        assert_eq!(game.get_cell(0, 1).content, CellContent::Number(1));
+        assert_eq!(game.get_cell(1, 1).content, CellContent::Number(0));
+
+        // Neighbours go both ways, and on a torus every cell has six. A
+        // torus with an odd number of rows is rejected, since there they
+        // wouldn't.
+        for height in [4, 3] {
+            let game = Game::from_text(&vec!["...."; height].join("\n"))
+                .unwrap()
+                .with_neighbourhood(Neighbourhood::Hex);
+            for topology in [Topology::Flat, Topology::Torus] {
+                if Game::check_shape(topology, Neighbourhood::Hex, height).is_err() {
+                    assert_eq!((topology, height), (Topology::Torus, 3));
+                    continue;
+                }
+                let game = game.clone().with_topology(topology);
+                for (x, y) in (0..height).flat_map(|y| (0..4).map(move |x| (x, y))) {
+                    let neighbours = game.neighbours(x, y);
+                    if topology == Topology::Torus {
+                        assert_eq!(neighbours.len(), 6);
+                    }
+                    for (nx, ny) in neighbours {
+                        assert!(game.neighbours(nx, ny).contains(&(x, y)));
+                    }
+                }
+            }
+        }
+        let odd = "minesweeper-rs save 1\nmines 0\npolicy unprotected\ntopology torus\nneighbourhood hex\nfirst_click false\nstate playing\nelapsed_ms 0\nboard\n. .\n. .\n. .\n";
+        assert!(matches!(
+            Game::from_save_text(odd),
+            Err(ParseGameError::OddHexTorus { rows: 3 })
+        ));
+        assert!(Game::from_save_text(&odd.replace(". .\n. .\n. .", ". .\n. .")).is_ok());
+    }
+}
@@ -0,0 +1,211 @@
+// Hints: the next move a careful player would make, with the reasoning
+// behind it. Certain moves come first - a single number or a pair of
+// numbers that decides a cell, then anything `get_deductions` finds - and
+// the lowest exact mine probability only when nothing is certain.
+
+use crate::game::{CellContent, CellState, Game, GameState};
+
+/// What a hint says about its cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HintKind {
+    /// Certainly safe - reveal it
+    Safe,
+    /// Certainly a mine - flag it
+    Mine,
+    /// Nothing is certain; this cell has the lowest mine probability
+    Guess(f64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hint {
+    pub kind: HintKind,
+    pub x: usize,
+    pub y: usize,
+    /// Why, in words, e.g. "the 1 at (2,3) ..." - or (x,y,z) on a 3D board
+    pub reason: String,
+}
+
+/// A revealed number and the covered (or flagged) cells around it.
+struct Clue {
+    at: (usize, usize),
+    /// `at` as the player sees it, see `Game::cell_name`
+    name: String,
+    number: u8,
+    cells: Vec<(usize, usize)>,
+}
+
+impl Clue {
+    fn describe(&self) -> String {
+        format!("the {} at {}", self.number, self.name)
+    }
+}
+
+impl Game {
+    fn clues(&self) -> Vec<Clue> {
+        let mut clues = Vec::new();
+        for y in 0..self.rows() {
+            for x in 0..self.width {
+                let cell = self.get_cell(x, y);
+                let CellContent::Number(number) = cell.content else {
+                    continue;
+                };
+                if cell.state != CellState::Revealed {
+                    continue;
+                }
+                let cells: Vec<(usize, usize)> = self
+                    .neighbours(x, y)
+                    .into_iter()
+                    .filter(|&(nx, ny)| self.get_cell(nx, ny).state != CellState::Revealed)
+                    .collect();
+                if !cells.is_empty() {
+                    clues.push(Clue {
+                        at: (x, y),
+                        name: self.cell_name(x, y),
+                        number,
+                        cells,
+                    });
+                }
+            }
+        }
+        clues
+    }
+
+    /// The next move to make, with the reasoning behind it: a cell that is
+    /// certainly safe, else a certain mine that isn't flagged yet, else the
+    /// covered cell with the lowest mine probability. Hints only point at
+    /// covered cells, and flags are never taken as mines. `None` if the game
+    /// is over or nothing is covered.
+    ///
+    /// Taking a hint marks the game as assisted.
+    pub fn hint(&mut self) -> Option<Hint> {
+        if self.state != GameState::Playing {
+            return None;
+        }
+        let hint = self.find_hint()?;
+        self.assisted = true;
+        Some(hint)
+    }
+
+    fn find_hint(&self) -> Option<Hint> {
+        let covered = |(x, y): (usize, usize)| self.get_cell(x, y).state == CellState::Covered;
+        let hint = |kind, (x, y): (usize, usize), reason: String| Hint { kind, x, y, reason };
+        let clues = self.clues();
+
+        // A number with as many covered neighbours as mines
+        for clue in &clues {
+            if clue.number as usize == clue.cells.len()
+                && let Some(&cell) = clue.cells.iter().find(|&&c| covered(c))
+            {
+                let reason = format!(
+                    "{} touches only {} covered cells, so they are all mines",
+                    clue.describe(),
+                    clue.cells.len()
+                );
+                return Some(hint(HintKind::Mine, cell, reason));
+            }
+        }
+
+        // Two numbers, where one sees a subset of the other's cells
+        for small in &clues {
+            for big in &clues {
+                if small.at == big.at || !small.cells.iter().all(|c| big.cells.contains(c)) {
+                    continue;
+                }
+                let rest: Vec<(usize, usize)> = big
+                    .cells
+                    .iter()
+                    .copied()
+                    .filter(|c| !small.cells.contains(c))
+                    .collect();
+                let extra = big.number as isize - small.number as isize;
+                if extra == 0
+                    && let Some(&cell) = rest.iter().find(|&&c| covered(c))
+                {
+                    let reason = format!(
+                        "{} and {} share the same mines, so {} is safe",
+                        small.describe(),
+                        big.describe(),
+                        self.cell_name(cell.0, cell.1)
+                    );
+                    return Some(hint(HintKind::Safe, cell, reason));
+                }
+                if extra > 0
+                    && extra as usize == rest.len()
+                    && let Some(&cell) = rest.iter().find(|&&c| covered(c))
+                {
+                    let reason = format!(
+                        "{} needs {extra} more mine(s) than {} can hold, so {} is a mine",
+                        big.describe(),
+                        small.describe(),
+                        self.cell_name(cell.0, cell.1)
+                    );
+                    return Some(hint(HintKind::Mine, cell, reason));
+                }
+            }
+        }
+
+        // Longer chains of reasoning, and the total mine count
+        let deductions = self.get_deductions();
+        let at = |i: usize| (i % self.width, i / self.width);
+        if let Some(&i) = deductions.safe.iter().find(|&&i| covered(at(i))) {
+            let (x, y) = at(i);
+            let reason = format!(
+                "{} is safe by combining the numbers and the mine count",
+                self.cell_name(x, y)
+            );
+            return Some(hint(HintKind::Safe, (x, y), reason));
+        }
+        if let Some(&i) = deductions.mines.iter().find(|&&i| covered(at(i))) {
+            let (x, y) = at(i);
+            let reason = format!(
+                "{} is a mine by combining the numbers and the mine count",
+                self.cell_name(x, y)
+            );
+            return Some(hint(HintKind::Mine, (x, y), reason));
+        }
+
+        // Nothing certain: the best guess
+        let probs = self.calculate_exact_bomb_probs();
+        let (x, y) = (0..self.rows())
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&c| covered(c))
+            .min_by(|&(ax, ay), &(bx, by)| {
+                probs[ay * self.width + ax].total_cmp(&probs[by * self.width + bx])
+            })?;
+        let prob = probs[y * self.width + x];
+        let reason = format!(
+            "No certain move - {} has the lowest chance of a mine, {:.0}%",
+            self.cell_name(x, y),
+            prob * 100.0
+        );
+        Some(hint(HintKind::Guess(prob), (x, y), reason))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints() {
+        // The 1-1 pattern along the top edge: (0,1) and (1,1) hold one mine,
+        // which is all the 1 at (1,0) needs, so (2,1) is safe
+        let mut game = Game::from_player_view("1 1 1 / # # #", 1).unwrap();
+        assert!(!game.assisted);
+        let hint = game.hint().unwrap();
+        assert_eq!(hint.kind, HintKind::Safe);
+        assert_eq!((hint.x, hint.y), (2, 1));
+        assert!(hint.reason.contains("the 1 at (0,0)"), "{}", hint.reason);
+        assert!(game.assisted);
+
+        let mut game = Game::from_player_view("1 # / # #", 1).unwrap();
+        let hint = game.hint().unwrap();
+        assert_eq!((hint.x, hint.y), (1, 0));
+        assert!(matches!(hint.kind, HintKind::Guess(p) if (p - 1.0 / 3.0).abs() < 1e-9));
+
+        let mut game = Game::from_player_view("1 1 / 1 #", 1).unwrap();
+        let hint = game.hint().unwrap();
+        assert_eq!(hint.kind, HintKind::Mine);
+        assert_eq!((hint.x, hint.y), (1, 1));
+    }
+}
@@ -0,0 +1,65 @@
+// Mouse input shared by the game and the video player: the buttons held
+// down, and the actions their presses and releases make.
+
+use crate::game::Action;
+
+/// A mouse button, for `Buttons`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+}
+
+/// The mouse buttons held down, turned into actions the way Arbiter plays:
+/// a left release reveals, a right press flags, and releasing one button
+/// while both are down (or a middle release) chords.
+#[derive(Debug, Default)]
+pub struct Buttons {
+    left: bool,
+    right: bool,
+    chorded: bool, // a chord has been made since both buttons went down
+}
+
+impl Buttons {
+    /// Handles a button press or release, returning the action it makes
+    /// (to be applied to the cell under the mouse), if any.
+    pub fn update(&mut self, button: Button, pressed: bool) -> Option<fn(usize, usize) -> Action> {
+        let action: Option<fn(usize, usize) -> Action> = match (button, pressed) {
+            (Button::Left, true) => {
+                self.left = true;
+                None
+            }
+            (Button::Right, true) => {
+                self.right = true;
+                (!self.left).then_some(Action::Flag)
+            }
+            (Button::Left, false) => {
+                self.left = false;
+                if self.right && !self.chorded {
+                    self.chorded = true;
+                    Some(Action::Chord)
+                } else if !self.right && !self.chorded {
+                    Some(Action::Reveal)
+                } else {
+                    None
+                }
+            }
+            (Button::Right, false) => {
+                self.right = false;
+                if self.left && !self.chorded {
+                    self.chorded = true;
+                    Some(Action::Chord)
+                } else {
+                    None
+                }
+            }
+            (Button::Middle, true) => None,
+            (Button::Middle, false) => Some(Action::Chord),
+        };
+        if !self.left && !self.right {
+            self.chorded = false;
+        }
+        action
+    }
+}
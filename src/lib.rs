@@ -1,6 +1,14 @@
 pub mod game;
+pub mod hex;
+pub mod hint;
+pub mod input;
+pub mod metrics;
+pub mod replay;
 pub mod solver;
+pub mod stats;
+pub mod strategy;
 pub mod tui;
+pub mod video;
 
 use clap::ValueEnum;
 
@@ -19,13 +27,130 @@ impl Difficulty {
             Difficulty::Expert => (30, 16, 99),
         }
     }
+
+    /// The preset with these dimensions, if any.
+    pub fn from_dimensions(width: usize, height: usize, num_mines: usize) -> Option<Self> {
+        Difficulty::value_variants()
+            .iter()
+            .copied()
+            .find(|d| d.dimensions() == (width, height, num_mines))
+    }
 }
 
-#[derive(ValueEnum, Copy, Clone, Debug)]
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum FirstClickPolicy {
     GuaranteedZero, // 0-cell (3x3 opening)
     GuaranteedSafe, // mine free
     Unprotected,    // can hit a mine
+    NoGuess,        // 0-cell, and the board can be solved by logic alone
+}
+
+/// How the edges of the board join up.
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Topology {
+    #[default]
+    Flat, // cells on the edge have fewer neighbours
+    Torus, // neighbours wrap around to the opposite edge
+}
+
+/// Which cells are neighbours: the cells a number counts mines in, and that
+/// a 0 opens.
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Neighbourhood {
+    #[default]
+    Moore, // the 8 surrounding cells
+    Orthogonal, // up, down, left and right only
+    Knight,     // the 8 cells a chess knight's move away
+    Radius2,    // the 24 cells of the surrounding 5x5 square
+    Hex,        // the 6 cells around a hexagon, see the `hex` module
+}
+
+#[rustfmt::skip]
+const MOORE: [(isize, isize); 8] = [
+    (-1, -1), (0, -1), (1, -1),
+    (-1, 0),           (1, 0),
+    (-1, 1),  (0, 1),  (1, 1),
+];
+#[rustfmt::skip]
+const ORTHOGONAL: [(isize, isize); 4] = [
+              (0, -1),
+    (-1, 0),           (1, 0),
+              (0, 1),
+];
+#[rustfmt::skip]
+const KNIGHT: [(isize, isize); 8] = [
+              (-1, -2),          (1, -2),
+    (-2, -1),                              (2, -1),
+
+    (-2, 1),                               (2, 1),
+              (-1, 2),           (1, 2),
+];
+#[rustfmt::skip]
+const RADIUS2: [(isize, isize); 24] = [
+    (-2, -2), (-1, -2), (0, -2), (1, -2), (2, -2),
+    (-2, -1), (-1, -1), (0, -1), (1, -1), (2, -1),
+    (-2, 0),  (-1, 0),           (1, 0),  (2, 0),
+    (-2, 1),  (-1, 1),  (0, 1),  (1, 1),  (2, 1),
+    (-2, 2),  (-1, 2),  (0, 2),  (1, 2),  (2, 2),
+];
+
+const MOORE_3D: [(isize, isize, isize); 26] = layered(&MOORE, &MOORE);
+const ORTHOGONAL_3D: [(isize, isize, isize); 6] = layered(&ORTHOGONAL, &[]);
+const KNIGHT_3D: [(isize, isize, isize); 10] = layered(&KNIGHT, &[]);
+const RADIUS2_3D: [(isize, isize, isize); 26] = layered(&RADIUS2, &[]);
+const HEX_3D: [(isize, isize, isize); 8] = layered(&hex::DIRECTIONS, &[]);
+
+/// The (dx, dy, dz) of `flat` within the layer, then for the layers above
+/// and below, the cell straight across and those at `around` from it.
+const fn layered<const N: usize>(
+    flat: &[(isize, isize)],
+    around: &[(isize, isize)],
+) -> [(isize, isize, isize); N] {
+    assert!(N == flat.len() + 2 * (around.len() + 1));
+    let mut offsets = [(0, 0, 0); N];
+    let mut i = 0;
+    while i < flat.len() {
+        offsets[i] = (flat[i].0, flat[i].1, 0);
+        i += 1;
+    }
+    let layer = around.len() + 1;
+    offsets[i] = (0, 0, -1);
+    offsets[i + layer] = (0, 0, 1);
+    let mut j = 0;
+    while j < around.len() {
+        offsets[i + 1 + j] = (around[j].0, around[j].1, -1);
+        offsets[i + layer + 1 + j] = (around[j].0, around[j].1, 1);
+        j += 1;
+    }
+    offsets
+}
+
+impl Neighbourhood {
+    /// The (dx, dy) of every neighbour, row by row - or for `Hex`, the
+    /// (dq, dr) in axial coordinates.
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Neighbourhood::Moore => &MOORE,
+            Neighbourhood::Orthogonal => &ORTHOGONAL,
+            Neighbourhood::Knight => &KNIGHT,
+            Neighbourhood::Radius2 => &RADIUS2,
+            Neighbourhood::Hex => &hex::DIRECTIONS,
+        }
+    }
+
+    /// The (dx, dy, dz) of every neighbour on a board of several layers:
+    /// the 26 cells of the surrounding cube for `Moore`, the 6 that share a
+    /// face for `Orthogonal`, and for the others the neighbours within the
+    /// layer plus the cells straight above and below.
+    pub fn offsets_3d(&self) -> &'static [(isize, isize, isize)] {
+        match self {
+            Neighbourhood::Moore => &MOORE_3D,
+            Neighbourhood::Orthogonal => &ORTHOGONAL_3D,
+            Neighbourhood::Knight => &KNIGHT_3D,
+            Neighbourhood::Radius2 => &RADIUS2_3D,
+            Neighbourhood::Hex => &HEX_3D,
+        }
+    }
 }
 
 // #[derive(Debug)]
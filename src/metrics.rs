@@ -0,0 +1,285 @@
+// Efficiency metrics that competitive players compare:
+//
+// * 3BV (Bechtel's Board Benchmark Value) - the minimum number of left
+//   clicks that clears the board without flags: one per opening (a region of
+//   0s plus the numbers around it) and one per number that doesn't border an
+//   opening.
+// * IOE - 3BV solved per click made.
+// * ZiNi - an estimate of the fewest clicks needed when flagging and
+//   chording are allowed, from the usual greedy procedure.
+
+use crate::game::{CellContent, CellState, Game, LogEvent};
+
+/// The 3BV units of a mine layout.
+struct Units {
+    /// The opening each cell belongs to. A number between two openings
+    /// belongs to the one found first.
+    opening: Vec<Option<usize>>,
+    openings: usize,
+    /// Numbers that don't border an opening - a click each
+    isolated: Vec<usize>,
+}
+
+impl Game {
+    /// The number under cell `i`, or `None` for a mine.
+    fn number_at(&self, i: usize) -> Option<u8> {
+        match self.get_cell(i % self.width, i / self.width).content {
+            CellContent::Number(n) => Some(n),
+            CellContent::Mine | CellContent::Explosion => None,
+        }
+    }
+
+    fn neighbour_indices(&self, i: usize) -> impl Iterator<Item = usize> + use<'_> {
+        self.neighbours(i % self.width, i / self.width)
+            .into_iter()
+            .map(|(x, y)| y * self.width + x)
+    }
+
+    fn units(&self) -> Option<Units> {
+        if !self.mines_placed() {
+            return None;
+        }
+        let n = self.width * self.rows();
+        let mut opening = vec![None; n];
+        let mut openings = 0;
+        for start in 0..n {
+            if self.number_at(start) != Some(0) || opening[start].is_some() {
+                continue;
+            }
+            opening[start] = Some(openings);
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                if self.number_at(i) != Some(0) {
+                    continue; // border number
+                }
+                for j in self.neighbour_indices(i) {
+                    if opening[j].is_none() {
+                        opening[j] = Some(openings);
+                        stack.push(j);
+                    }
+                }
+            }
+            openings += 1;
+        }
+        let isolated = (0..n)
+            .filter(|&i| opening[i].is_none() && self.number_at(i).is_some())
+            .collect();
+        Some(Units {
+            opening,
+            openings,
+            isolated,
+        })
+    }
+
+    /// Number of openings: connected regions of 0s. `None` until the mines
+    /// are placed.
+    pub fn openings(&self) -> Option<usize> {
+        self.units().map(|units| units.openings)
+    }
+
+    /// Number of islands: connected groups of numbers that don't border an
+    /// opening. `None` until the mines are placed.
+    pub fn islands(&self) -> Option<usize> {
+        let units = self.units()?;
+        let mut seen = vec![false; self.width * self.rows()];
+        let mut islands = 0;
+        for &start in &units.isolated {
+            if seen[start] {
+                continue;
+            }
+            islands += 1;
+            seen[start] = true;
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                for j in self.neighbour_indices(i) {
+                    if !seen[j] && units.opening[j].is_none() && self.number_at(j).is_some() {
+                        seen[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+        Some(islands)
+    }
+
+    /// The 3BV of the mine layout. `None` until the mines are placed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use minesweeper_rs::game::Game;
+    ///
+    /// // One opening along the bottom, and the 2 between the mines
+    /// let game = Game::from_text("*.*\n...\n...").unwrap();
+    /// assert_eq!(game.bbbv(), Some(2));
+    /// assert_eq!(game.openings(), Some(1));
+    /// assert_eq!(game.islands(), Some(1));
+    /// ```
+    pub fn bbbv(&self) -> Option<usize> {
+        self.units()
+            .map(|units| units.openings + units.isolated.len())
+    }
+
+    /// The part of the 3BV that has been cleared: openings with a revealed
+    /// 0, and revealed isolated numbers.
+    pub fn solved_bbbv(&self) -> Option<usize> {
+        let units = self.units()?;
+        let revealed =
+            |i: usize| self.get_cell(i % self.width, i / self.width).state == CellState::Revealed;
+        let mut solved = vec![false; units.openings];
+        for (i, opening) in units.opening.iter().enumerate() {
+            if let Some(opening) = *opening
+                && self.number_at(i) == Some(0)
+                && revealed(i)
+            {
+                solved[opening] = true;
+            }
+        }
+        let isolated = units.isolated.iter().filter(|&&i| revealed(i)).count();
+        Some(solved.iter().filter(|&&s| s).count() + isolated)
+    }
+
+    /// Clicks made: every reveal, flag and chord in the move log, including
+    /// the ones that had no effect.
+    pub fn clicks(&self) -> usize {
+        self.move_log()
+            .iter()
+            .filter(|entry| matches!(entry.event, LogEvent::Action(_)))
+            .count()
+    }
+
+    /// Efficiency: the 3BV solved per click. `None` before the first click.
+    pub fn ioe(&self) -> Option<f64> {
+        let clicks = self.clicks();
+        let solved = self.solved_bbbv()?;
+        (clicks > 0).then(|| solved as f64 / clicks as f64)
+    }
+
+    /// 3BV solved per second of a finished game.
+    pub fn bbbv_per_second(&self) -> Option<f64> {
+        let seconds = self.final_time?.as_secs_f64();
+        let solved = self.solved_bbbv()?;
+        (seconds > 0.0).then(|| solved as f64 / seconds)
+    }
+
+    /// ZiNi: an estimate of the fewest clicks that clear the board with
+    /// flags and chords. Repeatedly picks the number whose chord saves the
+    /// most clicks (revealing it if needed, flagging its mines and chording,
+    /// against clicking the units it opens one at a time), and clicks the
+    /// remaining units when no chord saves anything. `None` until the mines
+    /// are placed.
+    pub fn zini(&self) -> Option<usize> {
+        let units = self.units()?;
+        let n = self.width * self.rows();
+        let mut revealed = vec![false; n];
+        let mut flagged = vec![false; n];
+        let mut clicks = 0;
+
+        let reveal = |revealed: &mut Vec<bool>, i: usize| {
+            let mut stack = vec![i];
+            while let Some(i) = stack.pop() {
+                if revealed[i] {
+                    continue;
+                }
+                revealed[i] = true;
+                if self.number_at(i) == Some(0) {
+                    stack.extend(self.neighbour_indices(i));
+                }
+            }
+        };
+
+        loop {
+            // (premium, cell, cost) of the best chord
+            let mut best: Option<(isize, usize, isize)> = None;
+            for i in 0..n {
+                if !matches!(self.number_at(i), Some(1..)) {
+                    continue;
+                }
+                let mut cost = 1 + !revealed[i] as isize; // chord, and reveal first
+                let mut gain = (!revealed[i] && units.opening[i].is_none()) as isize;
+                let mut opened = Vec::new();
+                for j in self.neighbour_indices(i) {
+                    match self.number_at(j) {
+                        None => cost += !flagged[j] as isize,
+                        Some(_) if revealed[j] => {}
+                        Some(0) => {
+                            if !opened.contains(&units.opening[j]) {
+                                opened.push(units.opening[j]);
+                                gain += 1;
+                            }
+                        }
+                        Some(_) => gain += units.opening[j].is_none() as isize,
+                    }
+                }
+                let premium = gain - cost;
+                if best.is_none_or(|(p, _, _)| premium > p) {
+                    best = Some((premium, i, cost));
+                }
+            }
+            let Some((_, i, cost)) = best.filter(|&(premium, _, _)| premium > 0) else {
+                break;
+            };
+            clicks += cost as usize;
+            reveal(&mut revealed, i);
+            for j in self.neighbour_indices(i) {
+                match self.number_at(j) {
+                    None => flagged[j] = true,
+                    Some(_) => reveal(&mut revealed, j),
+                }
+            }
+        }
+
+        // Whatever is left is clicked one unit at a time
+        let mut opened = vec![false; units.openings];
+        for (i, opening) in units.opening.iter().enumerate() {
+            if let Some(opening) = *opening
+                && self.number_at(i) == Some(0)
+                && revealed[i]
+            {
+                opened[opening] = true;
+            }
+        }
+        clicks += opened.iter().filter(|&&o| !o).count();
+        clicks += units.isolated.iter().filter(|&&i| !revealed[i]).count();
+        Some(clicks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+
+    #[test]
+    fn test_metrics_of_a_single_mine() {
+        // Eight isolated 1s around the mine, no openings
+        let mut game = Game::from_text("...\n.*.\n...").unwrap();
+        assert_eq!(game.bbbv(), Some(8));
+        assert_eq!(game.openings(), Some(0));
+        assert_eq!(game.islands(), Some(1));
+        // Reveal an edge, flag the mine and chord it, then two more chords
+        assert_eq!(game.zini(), Some(5));
+
+        game.reveal(1, 0);
+        game.reveal(1, 0); // wasted click
+        game.flag(1, 1);
+        game.chord(1, 0);
+        game.chord(0, 1);
+        game.chord(2, 1);
+        assert_eq!(game.state, GameState::Won);
+        assert_eq!(game.solved_bbbv(), Some(8));
+        assert_eq!(game.clicks(), 6);
+        assert!((game.ioe().unwrap() - 8.0 / 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_shared_border_numbers() {
+        // The 2 in the middle borders both openings
+        let game = Game::from_text("*..\n...\n..*").unwrap();
+        assert_eq!(game.openings(), Some(2));
+        assert_eq!(game.bbbv(), Some(2));
+        assert_eq!(game.islands(), Some(0));
+        assert_eq!(game.zini(), Some(2));
+        assert_eq!(game.solved_bbbv(), Some(0));
+    }
+}
@@ -0,0 +1,406 @@
+// Replay files: the mine layout of a game plus its move log, so the game can
+// be played back move by move.
+//
+// Format (times in milliseconds since the first click):
+//
+//     minesweeper-rs replay 1
+//     board
+//     * . .
+//     . . .
+//     . . *
+//     events
+//     0 cursor 1 1
+//     0 reveal 1 1
+//     850 flag 0 0
+//     1200 chord 1 1
+//     1500 undo
+//     1700 redo
+//
+// The board uses '*' for mines and '.' for safe cells, as in `Game::from_text`.
+// Boards that aren't flat with 8 neighbours say so before `board`, e.g.
+// `topology torus` or `neighbourhood knight`, and 3D boards give their number
+// of layers with `depth 3`, the layers following one another in the board
+// with a blank line between them.
+
+use crate::game::{Action, Game, LoadGameError, LogEntry, LogEvent, ParseGameError};
+use crate::{Neighbourhood, Topology};
+use clap::ValueEnum;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+const REPLAY_HEADER: &str = "minesweeper-rs replay 1";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub width: usize,
+    /// Rows in each layer, as `Game::height`
+    pub height: usize,
+    /// Mine layout of every layer, indexed by `y * width + x` with the rows
+    /// of `Game::rows`
+    pub mines: Vec<bool>,
+    pub topology: Topology,
+    pub neighbourhood: Neighbourhood,
+    /// Number of layers of `width` x `height` cells, see `Game::depth`
+    pub depth: usize,
+    pub events: Vec<LogEntry>,
+}
+
+impl Replay {
+    /// Takes the mine layout and move log of a game. Returns `None` if the
+    /// mines haven't been placed yet (no first click).
+    ///
+    /// The layout is the one on the board now. If the first click was undone
+    /// and made somewhere else, the earlier moves are replayed on the new
+    /// layout.
+    pub fn from_game(game: &Game) -> Option<Self> {
+        if !game.mines_placed() {
+            return None;
+        }
+        let mines = (0..game.rows())
+            .flat_map(|y| (0..game.width).map(move |x| (x, y)))
+            .map(|(x, y)| game.is_mine(x, y))
+            .collect();
+        Some(Replay {
+            width: game.width,
+            height: game.height,
+            mines,
+            topology: game.topology(),
+            neighbourhood: game.neighbourhood(),
+            depth: game.depth(),
+            events: game.move_log().to_vec(),
+        })
+    }
+
+    /// A fresh game with the replay's mine layout and no moves made.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err` if the replay's fields don't make a board: no
+    /// cells, no layers, a mine layout of the wrong size, or a shape that
+    /// doesn't fit the rows.
+    pub fn new_game(&self) -> Result<Game, ParseGameError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(ParseGameError::EmptyInput);
+        }
+        if self.depth == 0 {
+            return Err(ParseGameError::InvalidField {
+                field: "depth".to_string(),
+                value: self.depth.to_string(),
+            });
+        }
+        if self.mines.len() != self.width * self.height * self.depth {
+            return Err(ParseGameError::InvalidField {
+                field: "board".to_string(),
+                value: format!(
+                    "{} cells for {}x{}x{}",
+                    self.mines.len(),
+                    self.width,
+                    self.height,
+                    self.depth
+                ),
+            });
+        }
+        Game::check_shape(self.topology, self.neighbourhood, self.height)?;
+        Ok(Game::from_text(&self.layout_text(""))?
+            .with_topology(self.topology)
+            .with_neighbourhood(self.neighbourhood))
+    }
+
+    /// The mine layout in `Game::from_text` format, with `separator`
+    /// between cells and a blank line between layers.
+    fn layout_text(&self, separator: &str) -> String {
+        let mut text = String::new();
+        // A width of 0 has no cells, and no rows to write
+        for (y, row) in self.mines.chunks(self.width.max(1)).enumerate() {
+            if y > 0 && y % self.height.max(1) == 0 {
+                text.push('\n');
+            }
+            let row: Vec<&str> = row.iter().map(|&m| if m { "*" } else { "." }).collect();
+            text.push_str(&row.join(separator));
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{REPLAY_HEADER}\n");
+        if self.topology != Topology::Flat {
+            let topology = self
+                .topology
+                .to_possible_value()
+                .expect("no skipped variants");
+            text.push_str(&format!("topology {}\n", topology.get_name()));
+        }
+        if self.neighbourhood != Neighbourhood::Moore {
+            let neighbourhood = self
+                .neighbourhood
+                .to_possible_value()
+                .expect("no skipped variants");
+            text.push_str(&format!("neighbourhood {}\n", neighbourhood.get_name()));
+        }
+        if self.depth > 1 {
+            text.push_str(&format!("depth {}\n", self.depth));
+        }
+        text.push_str(&format!("board\n{}events\n", self.layout_text(" ")));
+        for entry in &self.events {
+            let ms = entry.time.as_millis();
+            let event = match entry.event {
+                LogEvent::Cursor(x, y) => format!("cursor {x} {y}"),
+                LogEvent::Action(Action::Reveal(x, y)) => format!("reveal {x} {y}"),
+                LogEvent::Action(Action::Flag(x, y)) => format!("flag {x} {y}"),
+                LogEvent::Action(Action::Chord(x, y)) => format!("chord {x} {y}"),
+                LogEvent::Undo => "undo".to_string(),
+                LogEvent::Redo => "redo".to_string(),
+            };
+            text.push_str(&format!("{ms} {event}\n"));
+        }
+        text
+    }
+
+    /// Parses a replay written by `to_text`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err` if the header or a section is missing, the board is
+    /// not a valid grid, or an event can't be parsed.
+    pub fn from_text(text: &str) -> Result<Self, ParseGameError> {
+        let mut lines = text.trim().lines().map(str::trim).filter(|s| !s.is_empty());
+        match lines.next() {
+            Some(REPLAY_HEADER) => {}
+            Some(line) => {
+                return Err(ParseGameError::InvalidField {
+                    field: "header".to_string(),
+                    value: line.to_string(),
+                });
+            }
+            None => return Err(ParseGameError::EmptyInput),
+        }
+        let mut topology = Topology::Flat;
+        let mut neighbourhood = Neighbourhood::Moore;
+        let mut depth = 1;
+        loop {
+            let line = lines.next().ok_or(ParseGameError::MissingField("board"))?;
+            if line == "board" {
+                break;
+            }
+            let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+            let invalid = || ParseGameError::InvalidField {
+                field: field.to_string(),
+                value: value.to_string(),
+            };
+            match field {
+                "topology" => topology = Topology::from_str(value, true).map_err(|_| invalid())?,
+                "neighbourhood" => {
+                    neighbourhood = Neighbourhood::from_str(value, true).map_err(|_| invalid())?
+                }
+                "depth" => {
+                    depth = value.parse().map_err(|_| invalid())?;
+                    if depth == 0 {
+                        return Err(invalid());
+                    }
+                }
+                _ => return Err(ParseGameError::MissingField("board")),
+            }
+        }
+
+        // The blank lines between layers are skipped, and the layers told
+        // apart by their number
+        let layout: Vec<&str> = lines
+            .by_ref()
+            .take_while(|&line| line != "events")
+            .collect();
+        if layout.is_empty() {
+            return Err(ParseGameError::EmptyInput);
+        }
+        if !layout.len().is_multiple_of(depth) {
+            return Err(ParseGameError::InvalidField {
+                field: "depth".to_string(),
+                value: depth.to_string(),
+            });
+        }
+        let layers: Vec<String> = layout
+            .chunks(layout.len() / depth)
+            .map(|layer| layer.join("\n"))
+            .collect();
+        let game = Game::from_text(&layers.join("\n\n").replace(' ', ""))?;
+
+        let events = lines
+            .map(|line| parse_event(line, game.width, game.rows()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mines = (0..game.rows())
+            .flat_map(|y| (0..game.width).map(move |x| (x, y)))
+            .map(|(x, y)| game.is_mine(x, y))
+            .collect();
+        let replay = Replay {
+            width: game.width,
+            height: game.height,
+            mines,
+            topology,
+            neighbourhood,
+            depth,
+            events,
+        };
+        replay.new_game()?; // checks the shape fits the board
+        Ok(replay)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, LoadGameError> {
+        let content = fs::read_to_string(path)?;
+        Ok(Replay::from_text(&content)?)
+    }
+}
+
+/// Parses one event, whose cell (if any) must be on a board of `width`
+/// columns and `rows` rows (every layer's, see `Game::rows`).
+fn parse_event(line: &str, width: usize, rows: usize) -> Result<LogEntry, ParseGameError> {
+    let invalid = || ParseGameError::InvalidField {
+        field: "event".to_string(),
+        value: line.to_string(),
+    };
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let ms: u64 = parts
+        .first()
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(invalid)?;
+    let cell = || -> Result<(usize, usize), ParseGameError> {
+        match parts[2..] {
+            [x, y] => match (x.parse(), y.parse()) {
+                (Ok(x), Ok(y)) if x < width && y < rows => Ok((x, y)),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    };
+    let event = match parts.get(1).copied() {
+        Some("cursor") => {
+            let (x, y) = cell()?;
+            LogEvent::Cursor(x, y)
+        }
+        Some("reveal") => {
+            let (x, y) = cell()?;
+            LogEvent::Action(Action::Reveal(x, y))
+        }
+        Some("flag") => {
+            let (x, y) = cell()?;
+            LogEvent::Action(Action::Flag(x, y))
+        }
+        Some("chord") => {
+            let (x, y) = cell()?;
+            LogEvent::Action(Action::Chord(x, y))
+        }
+        Some("undo") if parts.len() == 2 => LogEvent::Undo,
+        Some("redo") if parts.len() == 2 => LogEvent::Redo,
+        _ => return Err(invalid()),
+    };
+    Ok(LogEntry {
+        time: Duration::from_millis(ms),
+        event,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FirstClickPolicy;
+    use crate::game::GameState;
+
+    #[test]
+    fn test_replay_round_trip() {
+        let mut game = Game::with_seed(9, 9, 10, FirstClickPolicy::GuaranteedZero, 2);
+        assert!(Replay::from_game(&game).is_none());
+        game.log_cursor(4, 4);
+        game.reveal(4, 4);
+        game.flag(0, 0);
+        game.undo();
+        game.redo();
+        let mine = (0..81).find(|&i| game.is_mine(i % 9, i / 9)).unwrap();
+        game.reveal(mine % 9, mine / 9);
+        assert_eq!(game.state, GameState::Lost);
+
+        let replay = Replay::from_game(&game).unwrap();
+        assert_eq!(replay.events.len(), 6);
+        let parsed = Replay::from_text(&replay.to_text()).unwrap();
+        // Times are stored with millisecond precision
+        assert_eq!(parsed.mines, replay.mines);
+        let events = |r: &Replay| r.events.iter().map(|e| e.event).collect::<Vec<_>>();
+        assert_eq!(events(&parsed), events(&replay));
+
+        // Playing the events back reproduces the game
+        let mut played = parsed.new_game().unwrap();
+        for entry in &parsed.events {
+            played.apply_log_event(entry.event);
+        }
+        assert_eq!(played.state, GameState::Lost);
+        assert_eq!(played.to_string(), game.to_string());
+    }
+
+    #[test]
+    fn test_replay_rejects_bad_event() {
+        let text = "minesweeper-rs replay 1\nboard\n* .\nevents\n10 reveal 1 0\n";
+        assert!(Replay::from_text(text).is_ok());
+        assert!(matches!(
+            Replay::from_text(&text.replace("reveal 1 0", "explode 1 0")),
+            Err(ParseGameError::InvalidField { .. })
+        ));
+        // Cells off the board
+        assert!(Replay::from_text(&text.replace("reveal 1 0", "cursor 99 99")).is_err());
+        assert!(Replay::from_text(&text.replace("reveal 1 0", "flag 0 1")).is_err());
+
+        let torus = text.replace("board", "topology torus\nneighbourhood knight\nboard");
+        let replay = Replay::from_text(&torus).unwrap();
+        assert_eq!(replay.topology, Topology::Torus);
+        assert_eq!(replay.neighbourhood, Neighbourhood::Knight);
+        assert_eq!(replay.to_text(), torus);
+        assert!(Replay::from_text(&text.replace("board", "topology sphere\nboard")).is_err());
+
+        let layers = text.replace("board\n* .", "depth 3\nboard\n* .\n\n. .\n\n. .");
+        let replay = Replay::from_text(&layers).unwrap();
+        assert_eq!((replay.height, replay.depth), (1, 3));
+        assert_eq!(replay.new_game().unwrap().depth(), 3);
+        assert_eq!(replay.to_text(), layers);
+        // The layers can also follow one another without blank lines
+        assert_eq!(
+            Replay::from_text(&layers.replace("\n\n", "\n")).unwrap(),
+            replay
+        );
+
+        // Replays built by hand are checked when they are played
+        let mut broken = replay.clone();
+        broken.width = 0;
+        assert!(broken.new_game().is_err());
+        broken.width = 3;
+        assert!(broken.new_game().is_err()); // 6 cells for 3x1x3
+        assert!(Replay::from_text(&text.replace("board", "depth 2\nboard")).is_err());
+    }
+
+    #[test]
+    fn test_replay_round_trip_3d() {
+        let mut game = Game::with_seed(4, 4, 6, FirstClickPolicy::GuaranteedSafe, 3).with_depth(2);
+        game.reveal(1, 5);
+        game.flag(3, 7);
+        let mine = (0..32).find(|&i| game.is_mine(i % 4, i / 4)).unwrap();
+        game.reveal(mine % 4, mine / 4);
+        assert_eq!(game.state, GameState::Lost);
+
+        let replay = Replay::from_game(&game).unwrap();
+        assert_eq!((replay.width, replay.height, replay.depth), (4, 4, 2));
+        assert_eq!(replay.mines.len(), 32);
+        let parsed = Replay::from_text(&replay.to_text()).unwrap();
+        assert_eq!(parsed.mines, replay.mines);
+        assert_eq!((parsed.height, parsed.depth), (4, 2));
+
+        let mut played = parsed.new_game().unwrap();
+        assert_eq!((played.height, played.rows()), (4, 8));
+        for entry in &parsed.events {
+            played.apply_log_event(entry.event);
+        }
+        assert_eq!(played.state, GameState::Lost);
+        assert_eq!(played.to_string(), game.to_string());
+    }
+}
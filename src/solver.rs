@@ -2,8 +2,15 @@
 // Reference:
 // "A simple Minesweeper algorithm", Mike Sheppard, October 9, 2023
 // https://minesweepergame.com/math/a-simple-minesweeper-algorithm-2023.pdf
+//
+// Also provides an exact back end (`solve_exact`) that enumerates the
+// mine assignments of each independent frontier component and weights
+// them by the number of ways the remaining mines fit in the sea of unknown.
+// `deduce` is a pure logic pass that finds certain cells without any
+// probability maths.
 
 use crate::Constraint;
+use std::collections::{BTreeSet, HashMap};
 
 const EPS: f64 = 1e-6;
 
@@ -44,6 +51,382 @@ pub fn solve_iterative_scaling(
     }
 }
 
+/// A set of local constraints that share cells, with the cells renumbered
+/// to local indices `0..cells.len()`.
+struct Component {
+    cells: Vec<usize>,                     // global cell indices
+    constraints: Vec<(Vec<usize>, usize)>, // local cell indices, mine count
+}
+
+/// Per-component enumeration result, indexed by the number of mines `k`
+/// the component holds.
+struct ComponentCounts {
+    /// Number of valid assignments with `k` mines
+    solutions: Vec<f64>,
+    /// For each `k`, the number of those assignments that mine each cell
+    mined: Vec<Vec<f64>>,
+}
+
+/// Splits the local constraints into groups that are connected through
+/// shared cells. Cells in different groups are independent apart from the
+/// global mine total.
+fn split_components(constraints: &[Constraint]) -> Vec<Component> {
+    let mut by_cell: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (ci, c) in constraints.iter().enumerate() {
+        for &cell in &c.cells {
+            by_cell.entry(cell).or_default().push(ci);
+        }
+    }
+
+    let mut seen = vec![false; constraints.len()];
+    let mut components = Vec::new();
+    for start in 0..constraints.len() {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut queue = vec![start];
+        let mut members = Vec::new();
+        let mut local: HashMap<usize, usize> = HashMap::new();
+        let mut cells = Vec::new();
+        // Breadth-first, so neighbouring cells get neighbouring local indices -
+        // this keeps constraints closing early during enumeration.
+        let mut head = 0;
+        while head < queue.len() {
+            let ci = queue[head];
+            head += 1;
+            members.push(ci);
+            for &cell in &constraints[ci].cells {
+                local.entry(cell).or_insert_with(|| {
+                    cells.push(cell);
+                    cells.len() - 1
+                });
+                for &other in &by_cell[&cell] {
+                    if !seen[other] {
+                        seen[other] = true;
+                        queue.push(other);
+                    }
+                }
+            }
+        }
+        let constraints = members
+            .iter()
+            .map(|&ci| {
+                let c = &constraints[ci];
+                let cells = c.cells.iter().map(|cell| local[cell]).collect();
+                (cells, c.count.round() as usize)
+            })
+            .collect();
+        components.push(Component { cells, constraints });
+    }
+    components
+}
+
+/// Backtracking state for enumerating the assignments of one component.
+struct Enumeration<'a> {
+    component: &'a Component,
+    cell_constraints: Vec<Vec<usize>>, // constraints touching each local cell
+    assignment: Vec<bool>,
+    placed: Vec<usize>, // mines placed so far, per constraint
+    open: Vec<usize>,   // cells not yet assigned, per constraint
+    counts: ComponentCounts,
+}
+
+impl Enumeration<'_> {
+    fn backtrack(&mut self, i: usize, mines: usize) {
+        if i == self.assignment.len() {
+            self.counts.solutions[mines] += 1.0;
+            for (cell, &is_mine) in self.assignment.iter().enumerate() {
+                if is_mine {
+                    self.counts.mined[mines][cell] += 1.0;
+                }
+            }
+            return;
+        }
+        for is_mine in [false, true] {
+            let mut feasible = true;
+            for &ci in &self.cell_constraints[i] {
+                self.open[ci] -= 1;
+                self.placed[ci] += is_mine as usize;
+                let target = self.component.constraints[ci].1;
+                if self.placed[ci] > target || self.placed[ci] + self.open[ci] < target {
+                    feasible = false;
+                }
+            }
+            if feasible {
+                self.assignment[i] = is_mine;
+                self.backtrack(i + 1, mines + is_mine as usize);
+                self.assignment[i] = false;
+            }
+            for &ci in &self.cell_constraints[i] {
+                self.open[ci] += 1;
+                self.placed[ci] -= is_mine as usize;
+            }
+        }
+    }
+}
+
+/// Enumerates every mine assignment of the component that satisfies all of
+/// its constraints, counting them by the number of mines used.
+fn enumerate_component(component: &Component) -> ComponentCounts {
+    let n = component.cells.len();
+    let mut cell_constraints = vec![Vec::new(); n];
+    for (ci, (cells, _)) in component.constraints.iter().enumerate() {
+        for &cell in cells {
+            cell_constraints[cell].push(ci);
+        }
+    }
+
+    let mut enumeration = Enumeration {
+        component,
+        cell_constraints,
+        assignment: vec![false; n],
+        placed: vec![0; component.constraints.len()],
+        open: component
+            .constraints
+            .iter()
+            .map(|(cells, _)| cells.len())
+            .collect(),
+        counts: ComponentCounts {
+            solutions: vec![0.0; n + 1],
+            mined: vec![vec![0.0; n]; n + 1],
+        },
+    };
+    enumeration.backtrack(0, 0);
+    enumeration.counts
+}
+
+fn convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut out = vec![0.0; a.len() + b.len() - 1];
+    for (i, &x) in a.iter().enumerate() {
+        if x == 0.0 {
+            continue;
+        }
+        for (j, &y) in b.iter().enumerate() {
+            out[i + j] += x * y;
+        }
+    }
+    out
+}
+
+/// Computes exact mine probabilities for every cell.
+///
+/// * `global` - all covered cells and the total number of mines
+/// * `constraints` - local constraints from the revealed numbers
+/// * `n_cells` - board size; cells outside `global` get probability 0
+///
+/// Covered cells that appear in no local constraint form the sea of unknown,
+/// where every cell is equally likely to hold a mine. Each frontier
+/// configuration with `t` mines is weighted by `C(sea, mines - t)`.
+///
+/// Returns `None` if the constraints admit no valid assignment.
+pub fn solve_exact(
+    global: &Constraint,
+    constraints: &[Constraint],
+    n_cells: usize,
+) -> Option<Vec<f64>> {
+    let total_mines = global.count.round() as usize;
+    let components = split_components(constraints);
+    let counts: Vec<ComponentCounts> = components.iter().map(enumerate_component).collect();
+
+    let mut in_frontier = vec![false; n_cells];
+    for component in &components {
+        for &cell in &component.cells {
+            in_frontier[cell] = true;
+        }
+    }
+    let sea: Vec<usize> = global
+        .cells
+        .iter()
+        .copied()
+        .filter(|&cell| !in_frontier[cell])
+        .collect();
+    let sea_size = sea.len();
+
+    // ln(n!) for n up to the sea size, so binomials can't overflow
+    let mut ln_fact = vec![0.0; sea_size + 1];
+    for n in 1..=sea_size {
+        ln_fact[n] = ln_fact[n - 1] + (n as f64).ln();
+    }
+    let ln_binom = |k: usize| ln_fact[sea_size] - ln_fact[k] - ln_fact[sea_size - k];
+
+    // Number of frontier configurations holding t mines, over all components
+    // (prefix[c] covers components before c, suffix[c] those from c onwards)
+    let mut prefix = vec![vec![1.0]];
+    for c in &counts {
+        prefix.push(convolve(prefix.last().unwrap(), &c.solutions));
+    }
+    let mut suffix = vec![vec![1.0]; counts.len() + 1];
+    for (ci, c) in counts.iter().enumerate().rev() {
+        suffix[ci] = convolve(&c.solutions, &suffix[ci + 1]);
+    }
+    let total = &prefix[counts.len()];
+
+    // Relative weight of a frontier holding t mines; scaled by the largest
+    // log-binomial in range so the exponentials stay finite.
+    let max_ln = (0..total.len())
+        .filter(|&t| t <= total_mines && total_mines - t <= sea_size)
+        .map(|t| ln_binom(total_mines - t))
+        .fold(f64::NEG_INFINITY, f64::max);
+    let weight = |t: usize| {
+        if t <= total_mines && total_mines - t <= sea_size {
+            (ln_binom(total_mines - t) - max_ln).exp()
+        } else {
+            0.0
+        }
+    };
+
+    let z: f64 = total.iter().enumerate().map(|(t, &n)| n * weight(t)).sum();
+    if z <= 0.0 || z.is_nan() {
+        return None;
+    }
+
+    let mut p = vec![0.0; n_cells];
+    for (ci, (component, c)) in components.iter().zip(&counts).enumerate() {
+        let rest = convolve(&prefix[ci], &suffix[ci + 1]);
+        for (k, mined) in c.mined.iter().enumerate() {
+            if c.solutions[k] == 0.0 {
+                continue;
+            }
+            let w: f64 = rest
+                .iter()
+                .enumerate()
+                .map(|(m, &n)| n * weight(k + m))
+                .sum();
+            for (local, &cell) in component.cells.iter().enumerate() {
+                p[cell] += mined[local] * w;
+            }
+        }
+        for &cell in &component.cells {
+            p[cell] /= z;
+        }
+    }
+
+    if sea_size > 0 {
+        let expected_sea_mines: f64 = total
+            .iter()
+            .enumerate()
+            .filter(|&(t, _)| t <= total_mines)
+            .map(|(t, &n)| n * weight(t) * (total_mines - t) as f64)
+            .sum::<f64>()
+            / z;
+        let sea_prob = expected_sea_mines / sea_size as f64;
+        for &cell in &sea {
+            p[cell] = sea_prob;
+        }
+    }
+
+    Some(p)
+}
+
+/// Cells that are certainly safe and certainly mines, given the constraints.
+#[derive(Debug, Default, PartialEq)]
+pub struct Deductions {
+    pub safe: Vec<usize>,
+    pub mines: Vec<usize>,
+}
+
+impl Deductions {
+    pub fn is_empty(&self) -> bool {
+        self.safe.is_empty() && self.mines.is_empty()
+    }
+}
+
+/// Finds certain-safe and certain-mine cells by logic alone:
+///
+/// * single point - a constraint with count 0 is all safe, one with
+///   count equal to its size is all mines
+/// * pairwise difference - for overlapping A and B, if
+///   `B.count - A.count == |B \ A|` then B \ A is all mines and A \ B is
+///   all safe. When A is a subset of B with equal counts, B \ A is all safe.
+///
+/// Known cells are substituted back into the constraints and the two rules
+/// repeated until nothing new is found. Constraints that contradict each
+/// other (no layout of mines meets them all) give empty `Deductions`.
+pub fn deduce(constraints: &[Constraint]) -> Deductions {
+    let mut known: HashMap<usize, bool> = HashMap::new(); // cell -> is mine
+    let mut constraints: Vec<(Vec<usize>, usize)> = constraints
+        .iter()
+        .map(|c| (c.cells.clone(), c.count.round() as usize))
+        .collect();
+
+    loop {
+        // Remove known cells from the constraints
+        let mut consistent = true;
+        for (cells, count) in constraints.iter_mut() {
+            cells.retain(|cell| match known.get(cell) {
+                Some(&is_mine) => {
+                    match count.checked_sub(is_mine as usize) {
+                        Some(rest) => *count = rest,
+                        None => consistent = false, // more mines than the count
+                    }
+                    false
+                }
+                None => true,
+            });
+            consistent &= *count <= cells.len();
+        }
+        if !consistent {
+            return Deductions::default();
+        }
+        constraints.retain(|(cells, _)| !cells.is_empty());
+        constraints.sort_unstable();
+        constraints.dedup();
+
+        let mut found: Vec<(Vec<usize>, bool)> = Vec::new();
+        for (cells, count) in &constraints {
+            if *count == 0 {
+                found.push((cells.clone(), false));
+            } else if *count == cells.len() {
+                found.push((cells.clone(), true));
+            }
+        }
+
+        if found.is_empty() {
+            for (a, a_count) in &constraints {
+                for (b, b_count) in &constraints {
+                    if a == b || !a.iter().any(|x| b.contains(x)) {
+                        continue;
+                    }
+                    let only_a: Vec<usize> = a.iter().copied().filter(|x| !b.contains(x)).collect();
+                    let only_b: Vec<usize> = b.iter().copied().filter(|x| !a.contains(x)).collect();
+                    if *b_count as isize - *a_count as isize == only_b.len() as isize {
+                        found.push((only_b, true));
+                        found.push((only_a, false));
+                    } else if only_a.is_empty() && a_count == b_count {
+                        found.push((only_b, false));
+                    }
+                }
+            }
+        }
+
+        if found.is_empty() {
+            break;
+        }
+        for (cells, is_mine) in found {
+            for cell in cells {
+                if known.insert(cell, is_mine) == Some(!is_mine) {
+                    return Deductions::default(); // concluded both ways
+                }
+            }
+        }
+    }
+
+    let mut safe = BTreeSet::new();
+    let mut mines = BTreeSet::new();
+    for (cell, is_mine) in known {
+        if is_mine {
+            mines.insert(cell);
+        } else {
+            safe.insert(cell);
+        }
+    }
+    Deductions {
+        safe: safe.into_iter().collect(),
+        mines: mines.into_iter().collect(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,4 +487,119 @@ mod tests {
             expected_q
         );
     }
+
+    /// Brute-force reference: enumerate every assignment of the global cells.
+    fn brute_force(global: &Constraint, constraints: &[Constraint], n_cells: usize) -> Vec<f64> {
+        let cells = &global.cells;
+        let mut p = vec![0.0; n_cells];
+        let mut total = 0.0;
+        for mask in 0u32..(1 << cells.len()) {
+            if mask.count_ones() as f64 != global.count {
+                continue;
+            }
+            let is_mine = |cell: usize| {
+                let pos = cells.iter().position(|&c| c == cell).unwrap();
+                mask & (1 << pos) != 0
+            };
+            let valid = constraints
+                .iter()
+                .all(|c| c.cells.iter().filter(|&&cell| is_mine(cell)).count() as f64 == c.count);
+            if valid {
+                total += 1.0;
+                for &cell in cells {
+                    if is_mine(cell) {
+                        p[cell] += 1.0;
+                    }
+                }
+            }
+        }
+        p.iter().map(|x| x / total).collect()
+    }
+
+    #[test]
+    fn test_exact_int_example() {
+        let global = Constraint::new(vec![0, 1, 2, 3, 4, 5], 3);
+        let constraints = vec![
+            Constraint::new(vec![1], 1),
+            Constraint::new(vec![0, 1, 2], 2),
+        ];
+
+        let p = solve_exact(&global, &constraints, 6).unwrap();
+
+        let expected = vec![0.5, 1.0, 0.5, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0];
+        assert!(
+            approx_eq_vec(&p, &expected, 1e-12),
+            "p = {:?}, expected = {:?}",
+            p,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_exact_matches_brute_force() {
+        // Two independent frontier components, a sea of 4 cells and
+        // a revealed cell (8) outside the global constraint.
+        let global = Constraint::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 9, 10, 11], 4);
+        let constraints = vec![
+            Constraint::new(vec![0, 1, 2], 1),
+            Constraint::new(vec![1, 2, 3], 2),
+            Constraint::new(vec![4, 5], 1),
+            Constraint::new(vec![5, 6], 1),
+        ];
+
+        let p = solve_exact(&global, &constraints, 12).unwrap();
+        let expected = brute_force(&global, &constraints, 12);
+        assert!(
+            approx_eq_vec(&p, &expected, 1e-12),
+            "p = {:?}, expected = {:?}",
+            p,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_exact_inconsistent() {
+        let global = Constraint::new(vec![0, 1], 1);
+        let constraints = vec![Constraint::new(vec![0, 1], 2)];
+        assert!(solve_exact(&global, &constraints, 2).is_none());
+    }
+
+    #[test]
+    fn test_deduce_one_two_one() {
+        // Revealed row "1 2 1" above covered cells 0..5:
+        // the 1-2-1 pattern puts mines under both 1s.
+        let constraints = vec![
+            Constraint::new(vec![0, 1, 2], 1),
+            Constraint::new(vec![1, 2, 3], 2),
+            Constraint::new(vec![2, 3, 4], 1),
+        ];
+        let d = deduce(&constraints);
+        assert_eq!(
+            d,
+            Deductions {
+                safe: vec![0, 2, 4],
+                mines: vec![1, 3],
+            }
+        );
+    }
+
+    #[test]
+    fn test_deduce_inconsistent() {
+        // A cell that must be both a mine and safe
+        let constraints = vec![Constraint::new(vec![2], 1), Constraint::new(vec![2], 0)];
+        assert!(deduce(&constraints).is_empty());
+        // Two certain mines under a 1
+        let constraints = vec![
+            Constraint::new(vec![0], 1),
+            Constraint::new(vec![1], 1),
+            Constraint::new(vec![0, 1], 1),
+        ];
+        assert!(deduce(&constraints).is_empty());
+    }
+
+    #[test]
+    fn test_deduce_nothing_certain() {
+        let constraints = vec![Constraint::new(vec![0, 1], 1)];
+        assert!(deduce(&constraints).is_empty());
+    }
 }
@@ -0,0 +1,393 @@
+// Statistics and best scores that outlive the process, kept per board size
+// and first-click policy in `$XDG_DATA_HOME/minesweeper-rs/stats.txt`.
+//
+// Format, one line per board and policy (times in milliseconds, '-' for
+// no record yet):
+//
+//     minesweeper-rs stats 1
+//     9 9 10 guaranteed-zero 12 3 2 5 7520 2.31
+//
+// The fields are width, height, mines, policy, wins, losses, current
+// streak, best streak, best time and best 3BV/s, followed by the topology,
+// neighbourhood and layers of boards that aren't the usual kind (e.g.
+// `torus knight 3-layers`). The height is that of each layer.
+
+use crate::game::{Game, GameState, LoadGameError, ParseGameError};
+use crate::{Difficulty, FirstClickPolicy, Neighbourhood, Topology};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const STATS_HEADER: &str = "minesweeper-rs stats 1";
+const LAYERS_SUFFIX: &str = "-layers";
+
+/// The record for one board and first-click policy.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32,
+    /// Wins in a row, up to the last game
+    pub streak: u32,
+    pub best_streak: u32,
+    pub best_time: Option<Duration>,
+    pub best_bbbv_per_second: Option<f64>,
+}
+
+/// What records are kept apart by: the board size, first-click policy and
+/// the shape of the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Board {
+    pub width: usize,
+    pub height: usize,
+    pub num_mines: usize,
+    pub policy: FirstClickPolicy,
+    pub topology: Topology,
+    pub neighbourhood: Neighbourhood,
+    pub depth: usize,
+}
+
+impl Board {
+    pub fn of(game: &Game) -> Self {
+        Board {
+            width: game.width,
+            height: game.height,
+            num_mines: game.num_mines,
+            policy: game.first_click_policy,
+            topology: game.topology(),
+            neighbourhood: game.neighbourhood(),
+            depth: game.depth(),
+        }
+    }
+
+    /// The names of the topology and neighbourhood and the number of
+    /// layers, leaving out those of the usual flat board with 8 neighbours.
+    fn shape(&self) -> Vec<String> {
+        let mut shape = Vec::new();
+        if self.topology != Topology::default() {
+            shape.push(value_name(self.topology));
+        }
+        if self.neighbourhood != Neighbourhood::default() {
+            shape.push(value_name(self.neighbourhood));
+        }
+        if self.depth > 1 {
+            shape.push(format!("{}{LAYERS_SUFFIX}", self.depth));
+        }
+        shape
+    }
+}
+
+fn value_name(value: impl ValueEnum) -> String {
+    let value = value.to_possible_value().expect("no skipped variants");
+    value.get_name().to_string()
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    records: BTreeMap<Board, Record>,
+}
+
+impl Stats {
+    /// `minesweeper-rs/stats.txt` in the XDG data directory:
+    /// `$XDG_DATA_HOME`, or `~/.local/share` if that isn't set. `None` if
+    /// neither it nor `$HOME` is set.
+    pub fn default_path() -> Option<PathBuf> {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share"))
+            })?;
+        Some(data_home.join("minesweeper-rs").join("stats.txt"))
+    }
+
+    /// Reads the stats file. A missing file gives empty stats.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LoadGameError> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Stats::from_text(&content)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Stats::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the stats file, creating its directory if needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_text())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{STATS_HEADER}\n");
+        for (board, record) in &self.records {
+            let best_time = match record.best_time {
+                Some(time) => time.as_millis().to_string(),
+                None => "-".to_string(),
+            };
+            let best_bbbv_per_second = match record.best_bbbv_per_second {
+                Some(rate) => format!("{rate:.4}"),
+                None => "-".to_string(),
+            };
+            let mut fields = vec![
+                board.width.to_string(),
+                board.height.to_string(),
+                board.num_mines.to_string(),
+                value_name(board.policy),
+                record.wins.to_string(),
+                record.losses.to_string(),
+                record.streak.to_string(),
+                record.best_streak.to_string(),
+                best_time,
+                best_bbbv_per_second,
+            ];
+            fields.extend(board.shape());
+            text.push_str(&fields.join(" "));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parses stats written by `to_text`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err` if the header is missing or a line can't be parsed.
+    pub fn from_text(text: &str) -> Result<Self, ParseGameError> {
+        let mut lines = text.lines().map(str::trim).filter(|s| !s.is_empty());
+        match lines.next() {
+            Some(STATS_HEADER) => {}
+            Some(line) => {
+                return Err(ParseGameError::InvalidField {
+                    field: "header".to_string(),
+                    value: line.to_string(),
+                });
+            }
+            None => return Err(ParseGameError::EmptyInput),
+        }
+
+        let mut stats = Stats::default();
+        for line in lines {
+            let invalid = || ParseGameError::InvalidField {
+                field: "stats".to_string(),
+                value: line.to_string(),
+            };
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (parts, shape) = parts.split_at(parts.len().min(10));
+            let [
+                width,
+                height,
+                mines,
+                policy,
+                wins,
+                losses,
+                streak,
+                best_streak,
+                time,
+                rate,
+            ] = parts[..]
+            else {
+                return Err(invalid());
+            };
+            let number = |s: &str| s.parse::<usize>().map_err(|_| invalid());
+            let count = |s: &str| s.parse::<u32>().map_err(|_| invalid());
+            let mut board = Board {
+                width: number(width)?,
+                height: number(height)?,
+                num_mines: number(mines)?,
+                policy: FirstClickPolicy::from_str(policy, true).map_err(|_| invalid())?,
+                topology: Topology::default(),
+                neighbourhood: Neighbourhood::default(),
+                depth: 1,
+            };
+            for &name in shape {
+                if let Ok(topology) = Topology::from_str(name, true) {
+                    board.topology = topology;
+                } else if let Some(depth) = name.strip_suffix(LAYERS_SUFFIX) {
+                    board.depth = number(depth)?;
+                    if board.depth == 0 {
+                        return Err(invalid());
+                    }
+                } else {
+                    board.neighbourhood =
+                        Neighbourhood::from_str(name, true).map_err(|_| invalid())?;
+                }
+            }
+            let record = Record {
+                wins: count(wins)?,
+                losses: count(losses)?,
+                streak: count(streak)?,
+                best_streak: count(best_streak)?,
+                best_time: match time {
+                    "-" => None,
+                    ms => Some(Duration::from_millis(ms.parse().map_err(|_| invalid())?)),
+                },
+                best_bbbv_per_second: match rate {
+                    "-" => None,
+                    rate => Some(rate.parse().map_err(|_| invalid())?),
+                },
+            };
+            stats.records.insert(board, record);
+        }
+        Ok(stats)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The record for a board, if any games were played on it.
+    pub fn get(&self, board: &Board) -> Option<&Record> {
+        self.records.get(board)
+    }
+
+    /// Adds a finished game. Wins and losses are always counted, but an
+    /// assisted win (e.g. after an undo) neither extends the streak nor sets
+    /// a best time or 3BV/s. Returns true if the game set a new best time.
+    /// Games still being played are ignored.
+    pub fn record(&mut self, game: &Game) -> bool {
+        if game.state == GameState::Playing {
+            return false;
+        }
+        let record = self.records.entry(Board::of(game)).or_default();
+        if game.state == GameState::Lost {
+            record.losses += 1;
+            record.streak = 0;
+            return false;
+        }
+
+        record.wins += 1;
+        if game.assisted {
+            return false;
+        }
+        record.streak += 1;
+        record.best_streak = record.best_streak.max(record.streak);
+        if let Some(rate) = game.bbbv_per_second() {
+            let best = record.best_bbbv_per_second.get_or_insert(rate);
+            *best = best.max(rate);
+        }
+        let Some(time) = game.final_time else {
+            return false;
+        };
+        let is_best = record.best_time.is_none_or(|best| time < best);
+        if is_best {
+            record.best_time = Some(time);
+        }
+        is_best
+    }
+}
+
+/// A table with a row per board size and policy.
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.records.is_empty() {
+            return writeln!(f, "No games recorded yet");
+        }
+        writeln!(
+            f,
+            "{:<14} {:<16} {:>5} {:>5} {:>6} {:>7} {:>5} {:>10} {:>7}",
+            "Board", "First click", "Won", "Lost", "Win %", "Streak", "Best", "Best time", "3BV/s"
+        )?;
+        for (board, record) in &self.records {
+            let (width, height, mines) = (board.width, board.height, board.num_mines);
+            let mut name = match Difficulty::from_dimensions(width, height, mines) {
+                Some(difficulty) => format!("{difficulty:?}"),
+                None => format!("{width}x{height}/{mines}"),
+            };
+            for shape in board.shape() {
+                name.push(' ');
+                name.push_str(&shape);
+            }
+            let played = record.wins + record.losses;
+            let win_rate = record.wins as f64 / played.max(1) as f64 * 100.0;
+            let best_time = record
+                .best_time
+                .map(|t| format!("{:.2}s", t.as_secs_f64()))
+                .unwrap_or_else(|| "-".to_string());
+            let rate = record
+                .best_bbbv_per_second
+                .map(|r| format!("{r:.2}"))
+                .unwrap_or_else(|| "-".to_string());
+            writeln!(
+                f,
+                "{name:<14} {:<16} {:>5} {:>5} {win_rate:>5.1}% {:>7} {:>5} {best_time:>10} {rate:>7}",
+                value_name(board.policy),
+                record.wins,
+                record.losses,
+                record.streak,
+                record.best_streak,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finished_game(win: bool, assisted: bool) -> Game {
+        let mut game = Game::from_text("*..\n...\n...").unwrap();
+        if win {
+            game.reveal(2, 2);
+        } else {
+            game.reveal(0, 0);
+        }
+        if assisted {
+            game.assisted = true;
+        }
+        game.final_time = Some(Duration::from_millis(if assisted { 100 } else { 2500 }));
+        game
+    }
+
+    #[test]
+    fn test_record_and_round_trip() {
+        let mut stats = Stats::default();
+        assert!(stats.record(&finished_game(true, false)));
+        assert!(!stats.record(&finished_game(true, true)));
+        assert!(!stats.record(&finished_game(false, false)));
+        assert!(!stats.record(&finished_game(true, false))); // not faster
+
+        let board = Board::of(&finished_game(true, false));
+        let record = stats.get(&board).unwrap().clone();
+        assert_eq!((record.wins, record.losses), (3, 1));
+        assert_eq!((record.streak, record.best_streak), (1, 1));
+        // The assisted win's 0.1s doesn't count
+        assert_eq!(record.best_time, Some(Duration::from_millis(2500)));
+        // A single opening (3BV 1) in 2.5s
+        assert_eq!(record.best_bbbv_per_second, Some(0.4));
+
+        // Other shapes of board are kept apart, a 3D one under the size of
+        // its layers
+        let layer = "...\n...\n...";
+        let mut cube = Game::from_text(&format!("*..\n...\n...\n\n{layer}\n\n{layer}"))
+            .unwrap()
+            .with_topology(Topology::Torus)
+            .with_neighbourhood(Neighbourhood::Orthogonal);
+        for y in 0..cube.rows() {
+            for x in 0..cube.width {
+                if !cube.is_mine(x, y) {
+                    cube.reveal(x, y);
+                }
+            }
+        }
+        assert_eq!(cube.state, GameState::Won);
+        cube.final_time = Some(Duration::from_millis(1000));
+        assert!(stats.record(&cube));
+        assert_eq!(stats.get(&board).unwrap().wins, 3);
+        let cube_board = Board::of(&cube);
+        assert_eq!((cube_board.height, cube_board.depth), (3, 3));
+        assert_eq!(stats.get(&cube_board).unwrap().wins, 1);
+
+        let parsed = Stats::from_text(&stats.to_text()).unwrap();
+        assert_eq!(parsed.to_text(), stats.to_text());
+        assert_eq!(parsed.get(&cube_board), stats.get(&cube_board));
+        assert!(stats.to_text().contains("\n3 3 1 unprotected 1 0 "));
+        assert!(stats.to_text().contains(" torus orthogonal 3-layers\n"));
+        assert!(Stats::from_text("minesweeper-rs stats 1\n9 9 10 sometimes\n").is_err());
+    }
+}
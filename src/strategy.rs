@@ -0,0 +1,243 @@
+// Solver bots: each strategy looks at a game the way a player would and
+// picks the next action. Strategies are stateless, so one instance can play
+// many games in parallel.
+
+use crate::game::{Action, CellContent, CellState, Game};
+use clap::ValueEnum;
+use rand::Rng;
+use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+
+pub trait Strategy: Sync {
+    /// Short name used in reports
+    fn name(&self) -> &'static str;
+
+    /// Picks the next action, or `None` if there is nothing left to do.
+    /// `rng` is used for random choices such as tie-breaks.
+    fn next_action(&self, game: &Game, rng: &mut StdRng) -> Option<Action>;
+}
+
+/// The strategies that ship with the library, for command-line selection.
+#[derive(ValueEnum, Copy, Clone, Debug)]
+pub enum StrategyKind {
+    Random,
+    LowestProbability,
+    LowestExactProbability,
+    DeductionFirst,
+    FlagAndChord,
+}
+
+impl StrategyKind {
+    pub fn build(&self) -> Box<dyn Strategy> {
+        match self {
+            StrategyKind::Random => Box::new(RandomStrategy),
+            StrategyKind::LowestProbability => Box::new(LowestProbability { exact: false }),
+            StrategyKind::LowestExactProbability => Box::new(LowestProbability { exact: true }),
+            StrategyKind::DeductionFirst => Box::new(DeductionFirst),
+            StrategyKind::FlagAndChord => Box::new(FlagAndChord),
+        }
+    }
+}
+
+fn covered_cells(game: &Game) -> Vec<(usize, usize)> {
+    (0..game.rows())
+        .flat_map(|y| (0..game.width).map(move |x| (x, y)))
+        .filter(|&(x, y)| game.get_cell(x, y).state == CellState::Covered)
+        .collect()
+}
+
+/// Picks a random covered cell among the `safe` ones. The deductions can also
+/// prove a flagged cell safe, and revealing that does nothing.
+fn reveal_safe(game: &Game, safe: &[usize], rng: &mut StdRng) -> Option<Action> {
+    let covered: Vec<usize> = safe
+        .iter()
+        .copied()
+        .filter(|&i| game.get_cell(i % game.width, i / game.width).state == CellState::Covered)
+        .collect();
+    let &i = covered.choose(rng)?;
+    Some(Action::Reveal(i % game.width, i / game.width))
+}
+
+/// Takes a flag off a cell the deductions prove safe, if the player put one there.
+fn unflag_safe(game: &Game, safe: &[usize]) -> Option<Action> {
+    safe.iter()
+        .map(|&i| (i % game.width, i / game.width))
+        .find(|&(x, y)| game.get_cell(x, y).state == CellState::Flagged)
+        .map(|(x, y)| Action::Flag(x, y))
+}
+
+/// Picks a covered cell with the lowest mine probability, breaking ties at random.
+fn lowest_probability(game: &Game, probs: &[f64], rng: &mut StdRng) -> Option<Action> {
+    let covered = covered_cells(game);
+    let prob = |&(x, y): &(usize, usize)| probs[y * game.width + x];
+    let min_prob = covered.iter().map(prob).fold(f64::INFINITY, f64::min);
+    let candidates: Vec<(usize, usize)> = covered
+        .into_iter()
+        .filter(|cell| (prob(cell) - min_prob).abs() < 1e-12)
+        .collect();
+    candidates.choose(rng).map(|&(x, y)| Action::Reveal(x, y))
+}
+
+/// Reveals a random covered cell - a baseline for the other bots.
+pub struct RandomStrategy;
+
+impl Strategy for RandomStrategy {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn next_action(&self, game: &Game, rng: &mut StdRng) -> Option<Action> {
+        let covered = covered_cells(game);
+        if covered.is_empty() {
+            return None;
+        }
+        let (x, y) = covered[rng.random_range(0..covered.len())];
+        Some(Action::Reveal(x, y))
+    }
+}
+
+/// Reveals the covered cell with the lowest mine probability, with a random
+/// tie-break. Uses `calculate_all_bomb_probs` (iterative scaling), or
+/// `calculate_exact_bomb_probs` if `exact` is set.
+pub struct LowestProbability {
+    pub exact: bool,
+}
+
+impl Strategy for LowestProbability {
+    fn name(&self) -> &'static str {
+        if self.exact {
+            "lowest-exact-probability"
+        } else {
+            "lowest-probability"
+        }
+    }
+
+    fn next_action(&self, game: &Game, rng: &mut StdRng) -> Option<Action> {
+        let probs = if self.exact {
+            game.calculate_exact_bomb_probs()
+        } else {
+            game.calculate_all_bomb_probs()
+        };
+        lowest_probability(game, &probs, rng)
+    }
+}
+
+/// Reveals a cell that `get_deductions` proves safe (after taking off any
+/// flag the player put on one), and only falls back to the lowest exact
+/// probability when logic alone gets stuck.
+pub struct DeductionFirst;
+
+impl Strategy for DeductionFirst {
+    fn name(&self) -> &'static str {
+        "deduction-first"
+    }
+
+    fn next_action(&self, game: &Game, rng: &mut StdRng) -> Option<Action> {
+        let safe = game.get_deductions().safe;
+        reveal_safe(game, &safe, rng)
+            .or_else(|| unflag_safe(game, &safe))
+            .or_else(|| lowest_probability(game, &game.calculate_exact_bomb_probs(), rng))
+    }
+}
+
+/// Plays like a human speed player: flags the certain mines, chords numbers
+/// whose mines are all flagged, and otherwise reveals certain-safe or
+/// lowest-probability cells.
+pub struct FlagAndChord;
+
+impl Strategy for FlagAndChord {
+    fn name(&self) -> &'static str {
+        "flag-and-chord"
+    }
+
+    fn next_action(&self, game: &Game, rng: &mut StdRng) -> Option<Action> {
+        let deductions = game.get_deductions();
+        for &i in &deductions.mines {
+            let (x, y) = (i % game.width, i / game.width);
+            if game.get_cell(x, y).state == CellState::Covered {
+                return Some(Action::Flag(x, y));
+            }
+        }
+
+        // Wrong flags would make chords reveal mines, so take them off first
+        if let Some(action) = unflag_safe(game, &deductions.safe) {
+            return Some(action);
+        }
+
+        // Only flags on certain mines are left, so these chords are safe
+        for y in 0..game.rows() {
+            for x in 0..game.width {
+                let cell = game.get_cell(x, y);
+                let CellContent::Number(n) = cell.content else {
+                    continue;
+                };
+                if cell.state != CellState::Revealed || n == 0 {
+                    continue;
+                }
+                let neighbours = game.neighbours(x, y);
+                let count = |state| {
+                    neighbours
+                        .iter()
+                        .filter(|&&(nx, ny)| game.get_cell(nx, ny).state == state)
+                        .count()
+                };
+                if count(CellState::Flagged) == n as usize && count(CellState::Covered) > 0 {
+                    return Some(Action::Chord(x, y));
+                }
+            }
+        }
+
+        if let Some(action) = reveal_safe(game, &deductions.safe, rng) {
+            return Some(action);
+        }
+        lowest_probability(game, &game.calculate_exact_bomb_probs(), rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FirstClickPolicy;
+    use crate::game::GameState;
+    use rand::SeedableRng;
+
+    fn play(strategy: &dyn Strategy, seed: u64) -> Game {
+        let mut game = Game::with_seed(9, 9, 10, FirstClickPolicy::NoGuess, seed);
+        game.reveal(4, 4);
+        finish(strategy, game, seed)
+    }
+
+    fn finish(strategy: &dyn Strategy, mut game: Game, seed: u64) -> Game {
+        let mut rng = StdRng::seed_from_u64(seed);
+        while game.state == GameState::Playing {
+            let action = strategy.next_action(&game, &mut rng).unwrap();
+            game.apply(action);
+        }
+        game
+    }
+
+    #[test]
+    fn test_logic_strategies_win_no_guess_boards() {
+        for kind in [StrategyKind::DeductionFirst, StrategyKind::FlagAndChord] {
+            for seed in 0..5 {
+                let game = play(kind.build().as_ref(), seed);
+                assert_eq!(game.state, GameState::Won, "{kind:?} seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_logic_strategies_take_off_wrong_flags() {
+        for kind in [StrategyKind::DeductionFirst, StrategyKind::FlagAndChord] {
+            for seed in 0..5 {
+                let mut game = Game::with_seed(9, 9, 10, FirstClickPolicy::NoGuess, seed);
+                game.reveal(4, 4);
+                for i in game.get_deductions().safe {
+                    game.flag(i % game.width, i / game.width);
+                }
+                let game = finish(kind.build().as_ref(), game, seed);
+                assert_eq!(game.state, GameState::Won, "{kind:?} seed {seed}");
+            }
+        }
+    }
+}
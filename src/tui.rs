@@ -1,16 +1,31 @@
-use crate::game::{CellContent, CellState, Game, GameState};
+use crate::game::{Action, CellContent, CellState, Game, GameState, LogEvent};
+use crate::hint::HintKind;
+use crate::input::{Button, Buttons};
+use crate::replay::Replay;
+use crate::stats::Stats;
+use crate::strategy::{Strategy, StrategyKind};
+use crate::{Neighbourhood, Topology};
 use crossterm::{
     cursor,
-    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     execute, queue,
     style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
     terminal::{self, Clear, ClearType},
 };
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use std::io::{self, Result, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 // --- CONFIGURATION & SYMBOLS ---
 const CELL_WIDTH: u16 = 3; // Each cell will be 3 characters wide
+const HEX_CELL_WIDTH: u16 = 4; // room to shift odd rows by half a cell
 const CURSOR_BG_COLOR: Color = Color::DarkYellow;
+const HINT_BG_COLOR: Color = Color::DarkGreen;
 
 // Use simple, single-width ASCII characters. They will be padded.
 const BOMB: char = '💣';
@@ -18,6 +33,7 @@ const FLAG: char = '🚩';
 const EXPLOSION: char = '💥';
 const COVERED: char = '#';
 const EMPTY: char = '.';
+const SAFEST: char = '✓';
 
 // Offsets for drawing the board on the screen
 const BOARD_OFFSET_X: u16 = 2;
@@ -29,24 +45,248 @@ pub struct Tui {
     cursor_y: usize,
     game: Game,
     show_bomb_probability: bool,
+    save_path: PathBuf,
+    message: Option<String>, // one-off feedback, shown until the next key press
+    clock: Option<Duration>, // replay time, shown instead of the game's own clock
+    stats_path: Option<PathBuf>, // finished games are recorded here, if set
+    recorded: bool,          // the current game's result is in the stats
+    probs: Vec<f64>,         // mine probabilities, see `refresh_probabilities`
+    probs_board: Option<Vec<CellState>>, // the board `probs` were worked out for
+    overlay: Overlay,
+    hint: Option<(usize, usize)>, // cell picked by the last hint, until the next key press
+    bot: Bot,
+    buttons: Buttons,     // mouse buttons held down
+    screen: (u16, u16),   // terminal size in columns and rows
+    view: (usize, usize), // board cell shown in the top-left corner of the board area
+}
+
+/// What the probability overlay shows on covered cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Overlay {
+    Off,
+    /// Colours each covered cell from green (safe) to red (mine)
+    Heat,
+    /// The heat colours, and a mark on the safest cell
+    HeatAndSafest,
+}
+
+/// The solver playing the game while the player watches, see `Tui::with_bot`.
+struct Bot {
+    strategy: Box<dyn Strategy>,
+    rng: StdRng,
+    delay: Duration, // between moves
+    running: bool,
+    paused: bool,
+    due: Instant, // when the next move is made
+}
+
+/// How fast `Tui::play_replay` steps through the events.
+#[derive(Clone, Copy, Debug)]
+pub enum ReplaySpeed {
+    /// Real time multiplied by the factor (1.0 is the original speed)
+    Factor(f64),
+    /// One event per key press
+    Step,
+}
+
+/// Background colour for a mine probability: green at 0, through yellow,
+/// to red at 1.
+fn heat_color(prob: f64) -> Color {
+    let prob = prob.clamp(0.0, 1.0);
+    Color::Rgb {
+        r: ((prob * 2.0).min(1.0) * 200.0) as u8,
+        g: (((1.0 - prob) * 2.0).min(1.0) * 170.0) as u8,
+        b: 0,
+    }
 }
 
 impl Tui {
-    pub fn new(game: Game, show_bomb_probability: bool) -> Result<Self> {
+    pub fn new(game: Game, show_bomb_probability: bool, save_path: PathBuf) -> Result<Self> {
         let mut stdout = io::stdout();
         terminal::enable_raw_mode()?;
-        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        execute!(
+            stdout,
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            event::EnableMouseCapture
+        )?;
         let cursor_x = game.width / 2;
-        let cursor_y = game.height / 2;
+        // The middle of the middle layer
+        let cursor_y = game.depth() / 2 * game.height + game.height / 2;
+        let recorded = game.state != GameState::Playing;
+        let screen = terminal::size().unwrap_or((80, 24));
         Ok(Tui {
             stdout,
             game,
             cursor_x,
             cursor_y,
             show_bomb_probability,
+            save_path,
+            message: None,
+            clock: None,
+            stats_path: None,
+            recorded,
+            probs: Vec::new(),
+            probs_board: None,
+            overlay: Overlay::Off,
+            hint: None,
+            bot: Bot {
+                strategy: StrategyKind::LowestProbability.build(),
+                rng: StdRng::from_rng(&mut rand::rng()),
+                delay: Duration::from_millis(500),
+                running: false,
+                paused: false,
+                due: Instant::now(),
+            },
+            buttons: Buttons::default(),
+            screen,
+            view: (0, 0),
         })
     }
 
+    /// Sets the bot that plays when 'a' is pressed, and the time between its
+    /// moves. With `watch`, the bot starts playing straight away.
+    pub fn with_bot(mut self, strategy: StrategyKind, delay: Duration, watch: bool) -> Self {
+        self.bot.strategy = strategy.build();
+        self.bot.delay = delay;
+        self.bot.running = watch;
+        self.bot.due = Instant::now() + delay;
+        self
+    }
+
+    /// Records the result of every game played in the stats file at `path`
+    /// (none if `None`).
+    pub fn with_stats(mut self, path: Option<PathBuf>) -> Self {
+        self.stats_path = path;
+        self
+    }
+
+    /// Number of columns and rows of cells that fit on the screen (at least
+    /// one of each, and no more than a layer of the board has). A row is
+    /// kept free below the board for the position indicator.
+    fn view_size(&self) -> (usize, usize) {
+        let (columns, rows) = (self.screen.0 as usize, self.screen.1 as usize);
+        let width = columns.saturating_sub((BOARD_OFFSET_X + self.row_shift(1)) as usize)
+            / self.cell_width() as usize;
+        let height = rows.saturating_sub(BOARD_OFFSET_Y as usize + 1);
+        (
+            width.clamp(1, self.game.width),
+            height.clamp(1, self.game.height),
+        )
+    }
+
+    /// The first row of the layer the cursor is on - the one on the screen.
+    fn layer_top(&self) -> usize {
+        self.cursor_y - self.cursor_y % self.game.height
+    }
+
+    /// Columns from one cell to the next.
+    fn cell_width(&self) -> u16 {
+        match self.game.neighbourhood() {
+            Neighbourhood::Hex => HEX_CELL_WIDTH,
+            _ => CELL_WIDTH,
+        }
+    }
+
+    /// How far row `y` is shifted right: half a cell for the odd rows of a
+    /// hex board, so each cell sits between the two it touches above.
+    fn row_shift(&self, y: usize) -> u16 {
+        if self.game.is_shifted(y) {
+            self.cell_width() / 2
+        } else {
+            0
+        }
+    }
+
+    /// Updates `probs` from `calculate_all_bomb_probs` if the board has
+    /// changed since they were worked out, so they are calculated once per
+    /// move rather than on every redraw.
+    fn refresh_probabilities(&mut self) {
+        let board: Vec<CellState> = (0..self.game.rows())
+            .flat_map(|y| (0..self.game.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.game.get_cell(x, y).state)
+            .collect();
+        if self.probs_board.as_ref() != Some(&board) {
+            self.probs = self.game.calculate_all_bomb_probs();
+            self.probs_board = Some(board);
+        }
+    }
+
+    /// The mine probability of the cell under the cursor (0 if revealed).
+    fn cursor_probability(&self) -> f64 {
+        let cell = self.game.get_cell(self.cursor_x, self.cursor_y);
+        match self
+            .probs
+            .get(self.cursor_y * self.game.width + self.cursor_x)
+        {
+            Some(&prob) if cell.state != CellState::Revealed => prob,
+            _ => 0.0,
+        }
+    }
+
+    /// The covered cell with the lowest mine probability, if any.
+    fn safest_cell(&self) -> Option<(usize, usize)> {
+        (0..self.game.rows())
+            .flat_map(|y| (0..self.game.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.game.get_cell(x, y).state == CellState::Covered)
+            .min_by(|&(ax, ay), &(bx, by)| {
+                let prob = |x, y| self.probs[y * self.game.width + x];
+                prob(ax, ay).total_cmp(&prob(bx, by))
+            })
+    }
+
+    /// Steps through the overlay modes: off, heat colours, heat colours and
+    /// the safest cell.
+    fn toggle_overlay(&mut self) {
+        let (overlay, message) = match self.overlay {
+            Overlay::Off => (Overlay::Heat, "Probability overlay on"),
+            Overlay::Heat => (
+                Overlay::HeatAndSafest,
+                "Probability overlay on, safest cell marked",
+            ),
+            Overlay::HeatAndSafest => (Overlay::Off, "Probability overlay off"),
+        };
+        self.overlay = overlay;
+        self.message = Some(message.to_string());
+    }
+
+    /// Scrolls the view as little as possible to bring the cursor into it.
+    /// The view's row is counted from the top of the cursor's layer.
+    fn scroll_to_cursor(&mut self) {
+        let (width, height) = self.view_size();
+        let follow = |view: usize, cursor: usize, size: usize, total: usize| {
+            let view = view.min(cursor).max((cursor + 1).saturating_sub(size));
+            view.min(total - size)
+        };
+        self.view = (
+            follow(self.view.0, self.cursor_x, width, self.game.width),
+            follow(
+                self.view.1,
+                self.cursor_y - self.layer_top(),
+                height,
+                self.game.height,
+            ),
+        );
+    }
+
+    /// Moves the cursor by whole screens of cells, stopping at the edges of
+    /// the layer. The view follows it.
+    fn page(&mut self, dx: isize, dy: isize) {
+        let (width, height) = self.view_size();
+        let top = self.layer_top();
+        let x = self.cursor_x as isize + dx * width as isize;
+        let y = (self.cursor_y - top) as isize + dy * height as isize;
+        self.cursor_x = x.clamp(0, self.game.width as isize - 1) as usize;
+        self.cursor_y = top + y.clamp(0, self.game.height as isize - 1) as usize;
+        self.game.log_cursor(self.cursor_x, self.cursor_y);
+    }
+
+    /// Moves the cursor to column `x` of its row. The view follows it.
+    fn jump_to_column(&mut self, x: usize) {
+        self.cursor_x = x;
+        self.game.log_cursor(self.cursor_x, self.cursor_y);
+    }
+
     fn move_cursor(&mut self, dx: isize, dy: isize) {
         // edges are hard - don't move cursor over
         // self.cursor_x = (self.cursor_x as isize + dx).clamp(0, self.width as isize - 1) as usize;
@@ -54,8 +294,25 @@ impl Tui {
         // wrap around when cursor moves over edge
         self.cursor_x =
             ((self.cursor_x as isize + dx).rem_euclid(self.game.width as isize)) as usize;
-        self.cursor_y =
-            ((self.cursor_y as isize + dy).rem_euclid(self.game.height as isize)) as usize;
+        let top = self.layer_top();
+        let height = self.game.height as isize;
+        self.cursor_y = top + ((self.cursor_y - top) as isize + dy).rem_euclid(height) as usize;
+        self.game.log_cursor(self.cursor_x, self.cursor_y);
+    }
+
+    /// Moves the cursor `dz` layers down through a 3D board, wrapping
+    /// around, to the same column and row of the layer. The screen shows
+    /// the layer the cursor is on.
+    fn change_layer(&mut self, dz: isize) {
+        let (depth, height) = (self.game.depth(), self.game.height);
+        if depth == 1 {
+            return;
+        }
+        let z = (self.cursor_y / height) as isize + dz;
+        let z = z.rem_euclid(depth as isize) as usize;
+        self.cursor_y = z * height + self.cursor_y % height;
+        self.game.log_cursor(self.cursor_x, self.cursor_y);
+        self.message = Some(format!("Layer {} of {depth}", z + 1));
     }
 
     /// Gets the character and color for a cell, but not its formatting or cursor highlight.
@@ -78,8 +335,8 @@ impl Tui {
                 CellContent::Number(7) => ('7', Color::Black),
                 CellContent::Number(8) => ('8', Color::DarkGrey),
                 CellContent::Number(n) => (
-                    // unreachable - at most 8 neighbours
-                    char::from_digit(n as u32, 10).unwrap_or('?'),
+                    // Only with a larger neighbourhood: 9, then a, b, ... for 10, 11, ...
+                    char::from_digit(n as u32, 36).unwrap_or('?'),
                     Color::Yellow,
                 ),
             },
@@ -96,12 +353,49 @@ impl Tui {
             ("", Color::White),
             ("CONTROLS:", Color::Yellow),
             ("  ↑↓←→ / hjkl    Move cursor", Color::White),
+            ("  PgUp/PgDn      Scroll a screen up/down", Color::White),
+            ("  Ctrl-←/→       Scroll a screen left/right", Color::White),
+            ("  Home/End       First/last column", Color::White),
+            ("  < / >          Layer up/down (3D boards)", Color::White),
             ("  R / Enter      Reveal cell", Color::White),
             ("  F / Space      Toggle flag", Color::White),
+            (
+                "  C              Chord (reveal around a number)",
+                Color::White,
+            ),
+            (
+                "  U / Ctrl-R     Undo / redo (marks the game assisted)",
+                Color::White,
+            ),
             ("  H / ?          This help", Color::White),
             ("  N              New game (when over)", Color::White),
+            (
+                "  P              Probability overlay (off/on/safest)",
+                Color::White,
+            ),
+            (
+                "  I              Hint (marks the game assisted)",
+                Color::White,
+            ),
+            (
+                "  A              Bot plays / take control back",
+                Color::White,
+            ),
+            (
+                "  Space          Pause the bot (instead of flagging while it plays)",
+                Color::White,
+            ),
+            ("  + / -          Bot faster / slower", Color::White),
+            ("  S              Save game", Color::White),
+            ("  W              Write a replay of the game", Color::White),
+            ("  T              Statistics", Color::White),
             ("  Q / Esc        Quit", Color::White),
             ("", Color::White),
+            ("MOUSE:", Color::Yellow),
+            ("  Left click     Reveal cell", Color::White),
+            ("  Right click    Toggle flag", Color::White),
+            ("  Middle / both  Chord", Color::White),
+            ("", Color::White),
             ("SYMBOLS:", Color::Yellow),
             (
                 &format!("  {COVERED:>3} Covered     {FLAG} Flagged     {EMPTY:<2}Empty"),
@@ -131,25 +425,54 @@ impl Tui {
         queue!(self.stdout, ResetColor)?;
         self.stdout.flush()?;
 
-        // Wait for a real key press (ignore releases and repeats)
+        self.wait_for_key(None)?;
+        Ok(())
+    }
+
+    /// Waits for a real key press (ignoring releases and repeats), for at
+    /// most `timeout` if given. Returns `None` on timeout. Resizes are
+    /// noted for the next redraw.
+    fn wait_for_key(&mut self, timeout: Option<Duration>) -> Result<Option<KeyCode>> {
+        let deadline = timeout.map(|t| Instant::now() + t);
         loop {
-            if let Event::Key(key_event) = event::read()? {
-                if key_event.kind == KeyEventKind::Press {
-                    break;
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if !event::poll(remaining)? {
+                    return Ok(None);
+                }
+            }
+            match event::read()? {
+                Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    return Ok(Some(key_event.code));
                 }
+                Event::Resize(columns, rows) => self.screen = (columns, rows),
+                _ => {}
             }
         }
-
-        Ok(())
     }
 
     /// Redraws the entire screen using explicit cursor positioning for stability.
     fn display(&mut self) -> Result<()> {
         //queue!(self.stdout, Clear(ClearType::All))?;
         // --- Draw static text ---
+        let torus = match self.game.topology() {
+            Topology::Flat => "",
+            Topology::Torus => ", torus",
+        };
+        let neighbours = match self.game.neighbourhood() {
+            Neighbourhood::Moore => "",
+            Neighbourhood::Orthogonal => ", orthogonal neighbours",
+            Neighbourhood::Knight => ", knight's move neighbours",
+            Neighbourhood::Radius2 => ", radius 2 neighbours",
+            Neighbourhood::Hex => ", hexagonal",
+        };
+        let size = match self.game.depth() {
+            1 => format!("{}x{}", self.game.width, self.game.height),
+            depth => format!("{}x{}x{depth}", self.game.width, self.game.height),
+        };
         let name = format!(
-            "{BOMB} MINESWEEPER{BOMB}  ({}x{}, {} mines)",
-            self.game.width, self.game.height, self.game.num_mines
+            "{BOMB} MINESWEEPER{BOMB}  ({size}, {} mines{torus}{neighbours})",
+            self.game.num_mines
         );
         queue!(
             self.stdout,
@@ -158,45 +481,180 @@ impl Tui {
             Print(name),
             cursor::MoveTo(0, 1),
             SetForegroundColor(Color::DarkGrey),
-            Print("Controls: ←↑↓→ Move | R Reveal | F Flag | Q Quit | ? Help")
+            Print(
+                "Controls: ←↑↓→ Move | R Reveal | F Flag | C Chord | U Undo | S Save | Q Quit | ? Help"
+            )
         )?;
 
-        // --- Draw game status ---
-        let elapsed_seconds = if let Some(duration) = self.game.final_time {
-            duration.as_secs()
-        } else if let Some(start) = self.game.start_time {
-            start.elapsed().as_secs()
+        // Probabilities are only worked out on a full redraw, not on ticks
+        if self.show_bomb_probability || self.overlay != Overlay::Off {
+            self.refresh_probabilities();
+        }
+        queue!(
+            self.stdout,
+            cursor::MoveTo(0, 2),
+            SetForegroundColor(Color::Yellow),
+            Print(self.message.as_deref().unwrap_or("")),
+            Clear(ClearType::UntilNewLine),
+        )?;
+        self.draw_status()?;
+
+        let show_all = self.game.state != GameState::Playing;
+
+        // --- Draw the visible part of the board ---
+        self.scroll_to_cursor();
+        let (view_width, view_height) = self.view_size();
+        let (view_x, view_y) = (self.view.0, self.layer_top() + self.view.1);
+        let heat = self.overlay != Overlay::Off && self.game.state == GameState::Playing;
+        let safest = if heat && self.overlay == Overlay::HeatAndSafest {
+            self.safest_cell()
         } else {
-            0
+            None
         };
+        for y in view_y..view_y + view_height {
+            for x in view_x..view_x + view_width {
+                // Calculate the top-left corner of the cell on the screen.
+                // The view fits on the screen, so these fit in a u16.
+                let screen_x = ((x - view_x) * self.cell_width() as usize) as u16
+                    + BOARD_OFFSET_X
+                    + self.row_shift(y);
+                let screen_y = (y - view_y) as u16 + BOARD_OFFSET_Y;
+
+                // Determine cell style
+                let (mut char, mut fg_color) = self.get_cell_style(x, y, show_all);
+                let is_cursor = x == self.cursor_x && y == self.cursor_y;
+                let heat_bg = (heat && self.game.get_cell(x, y).state == CellState::Covered)
+                    .then(|| heat_color(self.probs[y * self.game.width + x]));
+                if heat_bg.is_some() {
+                    fg_color = Color::Black;
+                }
+                if safest == Some((x, y)) {
+                    char = SAFEST;
+                }
+                let bg_color = if self.hint == Some((x, y)) {
+                    HINT_BG_COLOR
+                } else if is_cursor && self.game.state == GameState::Playing {
+                    CURSOR_BG_COLOR
+                } else {
+                    heat_bg.unwrap_or(Color::Black)
+                };
+
+                // Format the 3-character wide cell content
+                let display_string = format!(" {char}");
+
+                // Queue all commands for drawing one cell
+                queue!(
+                    self.stdout,
+                    cursor::MoveTo(screen_x, screen_y),
+                    SetForegroundColor(fg_color),
+                    SetBackgroundColor(bg_color),
+                    Print("   "), // clear
+                    cursor::MoveTo(screen_x, screen_y),
+                    Print(display_string),
+                )?;
+            }
+        }
+
+        // Which layer is shown, and where the view is when the board
+        // doesn't fit
+        let mut indicator = Vec::new();
+        if self.game.depth() > 1 {
+            indicator.push(format!(
+                "Layer {} of {} | </> change layer",
+                self.cursor_y / self.game.height + 1,
+                self.game.depth()
+            ));
+        }
+        if (view_width, view_height) != (self.game.width, self.game.height) {
+            indicator.push(format!(
+                "Columns {}-{} of {}, rows {}-{} of {} | PgUp/PgDn/Ctrl-←→ scroll",
+                view_x,
+                view_x + view_width - 1,
+                self.game.width,
+                self.view.1,
+                self.view.1 + view_height - 1,
+                self.game.height
+            ));
+        }
+        let indicator = indicator.join(" | ");
+        queue!(
+            self.stdout,
+            cursor::MoveTo(0, BOARD_OFFSET_Y + view_height as u16),
+            SetForegroundColor(Color::DarkGrey),
+            SetBackgroundColor(Color::Black),
+            Print(indicator),
+            Clear(ClearType::UntilNewLine)
+        )?;
+
+        queue!(self.stdout, ResetColor)?; // Reset colors at the very end
+        self.stdout.flush()
+    }
 
-        const M: &str = "Press 'n' for a new game.           "; // extra space: ensure line is cleared
+    /// Time shown on the status line: the replay clock, the final time of a
+    /// finished game, or the time since the first click.
+    fn elapsed(&self) -> Duration {
+        if let Some(clock) = self.clock {
+            clock
+        } else if let Some(duration) = self.game.final_time {
+            duration
+        } else if let Some(start) = self.game.start_time {
+            start.elapsed()
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    /// True while the game clock is running, so the status line needs
+    /// redrawing every second.
+    fn clock_running(&self) -> bool {
+        self.clock.is_none()
+            && self.game.state == GameState::Playing
+            && self.game.mines_placed()
+            && self.game.start_time.is_some()
+    }
+
+    /// Draws the status line (row 3) - all that changes on a clock tick.
+    fn draw_status(&mut self) -> Result<()> {
+        let elapsed = self.elapsed();
+        let elapsed_seconds = elapsed.as_secs();
+
+        const M: &str = "Press 'n' for a new game.";
         let status = match self.game.state {
             GameState::Playing => {
                 let flags = self.game.count(CellState::Flagged);
                 let covered = self.game.count(CellState::Covered);
-
                 let prob_display = if self.show_bomb_probability {
-                    let prob = if covered + flags == self.game.width * self.game.height {
-                        self.game.num_mines as f64 / (covered + flags) as f64
-                    } else {
-                        self.game.get_bomb_prob(self.cursor_x, self.cursor_y)
-                    };
                     format!(
-                        " | Mine @ ({},{}): {prob:4.2}",
-                        self.cursor_x, self.cursor_y
+                        " | Mine @ {}: {:4.2}",
+                        self.game.cell_name(self.cursor_x, self.cursor_y),
+                        self.cursor_probability()
                     )
                 } else {
                     String::new()
                 };
-
+                let no_guess = if self.game.no_guess_failed() {
+                    " | No guess-free board found, this one may need a guess"
+                } else {
+                    ""
+                };
                 format!(
-                    "Mines: {} | Flags: {flags} | Covered: {covered}{prob_display}              ",
+                    "Time: {elapsed_seconds}s | Mines: {} | Flags: {flags} | Covered: {covered}{prob_display}{no_guess}",
                     self.game.num_mines
                 )
             }
             GameState::Won => {
-                format!("🎉 You Won! Time: {elapsed_seconds}s. {M}")
+                let assisted = if self.game.assisted {
+                    " (assisted)"
+                } else {
+                    ""
+                };
+                let bbbv = self.game.bbbv().unwrap_or_default();
+                let seconds = elapsed.as_secs_f64().max(0.001);
+                format!(
+                    "🎉 You Won! Time: {:.2}s{assisted} | 3BV: {bbbv} | 3BV/s: {:.2}. {M}",
+                    elapsed.as_secs_f64(),
+                    bbbv as f64 / seconds
+                )
             }
             GameState::Lost => {
                 format!("💥 Game Over! Time: {elapsed_seconds}s. {M}")
@@ -207,83 +665,424 @@ impl Tui {
             self.stdout,
             cursor::MoveTo(0, 3),
             SetForegroundColor(Color::White),
-            Print(status)
+            Print(status),
+            Clear(ClearType::UntilNewLine),
+            ResetColor
+        )
+    }
+
+    /// True while the bot is due to make moves.
+    fn bot_active(&self) -> bool {
+        self.bot.running && !self.bot.paused && self.game.state == GameState::Playing
+    }
+
+    /// Waits for the next terminal event. While the clock runs, the status
+    /// line is redrawn whenever the elapsed seconds change. Returns `None`
+    /// when it is time for the bot to move.
+    fn next_event(&mut self) -> Result<Option<Event>> {
+        loop {
+            let bot_wait = self
+                .bot_active()
+                .then(|| self.bot.due.saturating_duration_since(Instant::now()));
+            let tick = self.clock_running().then(|| {
+                Duration::from_secs(1) - Duration::from_nanos(self.elapsed().subsec_nanos() as u64)
+            });
+            let timeout = match (bot_wait, tick) {
+                (Some(bot_wait), Some(tick)) => bot_wait.min(tick),
+                (Some(timeout), None) | (None, Some(timeout)) => timeout,
+                (None, None) => return event::read().map(Some),
+            };
+            if event::poll(timeout)? {
+                return event::read().map(Some);
+            }
+            if self.bot_active() && Instant::now() >= self.bot.due {
+                return Ok(None);
+            }
+            self.draw_status()?;
+            self.stdout.flush()?;
+        }
+    }
+
+    /// Shows the table of wins, streaks and best scores.
+    fn display_stats(&mut self) -> Result<()> {
+        let (title, table) = match &self.stats_path {
+            Some(path) => match Stats::load(path) {
+                Ok(stats) => (
+                    format!("STATISTICS ({})", path.display()),
+                    stats.to_string(),
+                ),
+                Err(err) => (
+                    "STATISTICS".to_string(),
+                    format!("Could not read {}: {err}", path.display()),
+                ),
+            },
+            None => (
+                "STATISTICS".to_string(),
+                "Statistics are not being kept".to_string(),
+            ),
+        };
+        queue!(
+            self.stdout,
+            Clear(ClearType::All),
+            cursor::MoveTo(2, 1),
+            SetForegroundColor(Color::Cyan),
+            Print(title),
+            SetForegroundColor(Color::White)
+        )?;
+        let lines = table.lines().count() as u16;
+        for (i, line) in table.lines().enumerate() {
+            queue!(self.stdout, cursor::MoveTo(2, i as u16 + 3), Print(line))?;
+        }
+        queue!(
+            self.stdout,
+            cursor::MoveTo(2, lines + 4),
+            SetForegroundColor(Color::Cyan),
+            Print("Press any key to continue..."),
+            ResetColor
         )?;
+        self.stdout.flush()?;
+        self.wait_for_key(None)?;
+        Ok(())
+    }
 
-        let show_all = self.game.state != GameState::Playing;
+    /// Adds the game to the stats file once it is over. Undoing the last
+    /// move and finishing again doesn't count twice.
+    fn record_result(&mut self) {
+        if self.recorded || self.game.state == GameState::Playing {
+            return;
+        }
+        self.recorded = true;
+        let Some(path) = &self.stats_path else {
+            return;
+        };
+        let result = Stats::load(path)
+            .map_err(|err| err.to_string())
+            .and_then(|mut stats| {
+                let best = stats.record(&self.game);
+                stats
+                    .save(path)
+                    .map(|()| best)
+                    .map_err(|err| err.to_string())
+            });
+        match result {
+            Ok(true) => self.message = Some("New best time!".to_string()),
+            Ok(false) => {}
+            Err(err) => self.message = Some(format!("Could not update {}: {err}", path.display())),
+        }
+    }
 
-        // --- Draw board with explicit cursor positioning ---
-        for y in 0..self.game.height {
-            for x in 0..self.game.width {
-                // Calculate the top-left corner of the cell on the screen
-                let screen_x = x as u16 * CELL_WIDTH + BOARD_OFFSET_X;
-                let screen_y = y as u16 + BOARD_OFFSET_Y;
+    fn save(&mut self) {
+        let path = self.save_path.display();
+        self.message = Some(match self.game.save(&self.save_path) {
+            Ok(()) => format!("Saved to {path}"),
+            Err(err) => format!("Could not save to {path}: {err}"),
+        });
+    }
 
-                // Determine cell style
-                let (char, fg_color) = self.get_cell_style(x, y, show_all);
-                let is_cursor = x == self.cursor_x && y == self.cursor_y;
-                let bg_color = if is_cursor && self.game.state == GameState::Playing {
-                    CURSOR_BG_COLOR
+    fn write_replay(&mut self) {
+        let path = self.save_path.with_extension("replay");
+        self.message = Some(match Replay::from_game(&self.game) {
+            None => "Nothing to replay yet".to_string(),
+            Some(replay) => match replay.save(&path) {
+                Ok(()) => format!("Replay written to {}", path.display()),
+                Err(err) => format!("Could not write {}: {err}", path.display()),
+            },
+        });
+    }
+
+    /// Plays back a replay on this TUI's game, which should be
+    /// `replay.new_game()`. Space pauses and resumes, any other key steps
+    /// while paused, and Q quits.
+    pub fn play_replay(&mut self, replay: &Replay, speed: ReplaySpeed) -> Result<()> {
+        let (factor, step_only) = match speed {
+            ReplaySpeed::Factor(factor) => (factor, false),
+            ReplaySpeed::Step => (1.0, true),
+        };
+        let mut paused = step_only;
+        let mut previous = Duration::ZERO;
+        self.clock = Some(Duration::ZERO);
+        self.display()?;
+
+        for entry in &replay.events {
+            if !paused {
+                let wait = entry.time.saturating_sub(previous).div_f64(factor);
+                let deadline = Instant::now() + wait;
+                let remaining = || deadline.saturating_duration_since(Instant::now());
+                while let Some(code) = self.wait_for_key(Some(remaining()))? {
+                    match code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Char(' ') => {
+                            paused = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            if paused {
+                self.message = Some(if step_only {
+                    "Step mode: any key for the next move, Q to quit".to_string()
                 } else {
-                    Color::Black
-                };
+                    "Paused: any key to step, Space to resume, Q to quit".to_string()
+                });
+                self.display()?;
+                match self.wait_for_key(None)? {
+                    Some(KeyCode::Char('q') | KeyCode::Esc) => return Ok(()),
+                    Some(KeyCode::Char(' ')) if !step_only => paused = false,
+                    _ => {}
+                }
+            }
+            previous = entry.time;
 
-                // Format the 3-character wide cell content
-                let display_string = format!(" {char}");
+            match entry.event {
+                LogEvent::Cursor(x, y) => (self.cursor_x, self.cursor_y) = (x, y),
+                LogEvent::Action(
+                    action @ (Action::Reveal(x, y) | Action::Flag(x, y) | Action::Chord(x, y)),
+                ) => {
+                    (self.cursor_x, self.cursor_y) = (x, y);
+                    self.game.apply(action);
+                }
+                event => self.game.apply_log_event(event),
+            }
+            self.clock = Some(entry.time);
+            self.display()?;
+        }
 
-                // Queue all commands for drawing one cell
-                queue!(
-                    self.stdout,
-                    cursor::MoveTo(screen_x, screen_y),
-                    SetForegroundColor(fg_color),
-                    SetBackgroundColor(bg_color),
-                    Print("   "), // clear
-                    cursor::MoveTo(screen_x, screen_y),
-                    Print(display_string),
-                )?;
+        self.message = Some("End of replay - press any key to exit".to_string());
+        self.display()?;
+        self.wait_for_key(None)?;
+        Ok(())
+    }
+
+    /// Asks the solver for the next move: moves the cursor to it, flags it
+    /// if it is a certain mine, and shows the reasoning.
+    fn hint(&mut self) {
+        let Some(hint) = self.game.hint() else {
+            return;
+        };
+        self.hover((hint.x, hint.y));
+        if hint.kind == HintKind::Mine {
+            self.game.flag(hint.x, hint.y);
+        } else {
+            self.hint = Some((hint.x, hint.y));
+        }
+        self.message = Some(format!("Hint: {}", hint.reason));
+    }
+
+    /// Lets the bot make one move: highlights the cell it picked, with its
+    /// mine probability, and plays it. Games the bot has played in are
+    /// marked assisted, so its wins don't set best times or streaks.
+    fn bot_step(&mut self) {
+        self.bot.due = Instant::now() + self.bot.delay;
+        let Some(action) = self.bot.strategy.next_action(&self.game, &mut self.bot.rng) else {
+            self.bot.running = false;
+            self.message = Some("The bot has no move to make".to_string());
+            return;
+        };
+        let (verb, x, y) = match action {
+            Action::Reveal(x, y) => ("reveal", x, y),
+            Action::Flag(x, y) => ("flag", x, y),
+            Action::Chord(x, y) => ("chord", x, y),
+        };
+        self.refresh_probabilities();
+        let prob = self.probs[y * self.game.width + x];
+        self.hover((x, y));
+        self.hint = Some((x, y));
+        self.game.assisted = true;
+        self.game.apply(action);
+        self.message = Some(format!(
+            "Bot ({}): {verb} {}, mine probability {:.0}% | Space pause, A take over, +/- speed",
+            self.bot.strategy.name(),
+            self.game.cell_name(x, y),
+            prob * 100.0
+        ));
+    }
+
+    /// Hands the game to the bot, or takes it back.
+    fn toggle_bot(&mut self) {
+        self.bot.running = !self.bot.running;
+        self.bot.paused = false;
+        self.bot.due = Instant::now() + self.bot.delay;
+        self.message = Some(if self.bot.running {
+            format!(
+                "The {} bot is playing - Space pause, A take over, +/- speed",
+                self.bot.strategy.name()
+            )
+        } else {
+            "You have control".to_string()
+        });
+    }
+
+    fn pause_bot(&mut self) {
+        self.bot.paused = !self.bot.paused;
+        self.bot.due = Instant::now() + self.bot.delay;
+        self.message = Some(if self.bot.paused {
+            "Bot paused - Space to continue, A take over".to_string()
+        } else {
+            "Bot playing".to_string()
+        });
+    }
+
+    /// Multiplies the time between bot moves by `factor`, within 10ms to 5s.
+    fn bot_speed(&mut self, factor: f64) {
+        self.bot.delay = self
+            .bot
+            .delay
+            .mul_f64(factor)
+            .clamp(Duration::from_millis(10), Duration::from_secs(5));
+        self.message = Some(format!("Bot moves every {}ms", self.bot.delay.as_millis()));
+    }
+
+    fn undo(&mut self) {
+        if !self.game.undo() {
+            self.message = Some("Nothing to undo".to_string());
+        }
+    }
+
+    fn redo(&mut self) {
+        if !self.game.redo() {
+            self.message = Some("Nothing to redo".to_string());
+        }
+    }
+
+    /// The cell drawn at a terminal position, if any.
+    fn cell_at(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        let y = row.checked_sub(BOARD_OFFSET_Y)? as usize;
+        let top = self.layer_top() + self.view.1;
+        let shift = BOARD_OFFSET_X + self.row_shift(top + y);
+        let x = (column.checked_sub(shift)? / self.cell_width()) as usize;
+        let (width, height) = self.view_size();
+        (x < width && y < height).then_some((self.view.0 + x, top + y))
+    }
+
+    /// Hovering moves the cursor, and clicks act on the cell under the
+    /// mouse (see `Buttons`). Returns false if nothing changed, so the
+    /// screen needn't be redrawn.
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> bool {
+        let cell = self.cell_at(mouse.column, mouse.row);
+        let (button, pressed) = match mouse.kind {
+            MouseEventKind::Down(button) => (button, true),
+            MouseEventKind::Up(button) => (button, false),
+            MouseEventKind::Moved | MouseEventKind::Drag(_) => {
+                return match cell {
+                    Some(cell) if self.game.state == GameState::Playing => self.hover(cell),
+                    _ => false,
+                };
             }
+            _ => return false,
+        };
+        let button = match button {
+            MouseButton::Left => Button::Left,
+            MouseButton::Right => Button::Right,
+            MouseButton::Middle => Button::Middle,
+        };
+        let action = self.buttons.update(button, pressed);
+        match (action, cell) {
+            (Some(action), Some((x, y))) if self.game.state == GameState::Playing => {
+                self.message = None;
+                self.hint = None;
+                self.hover((x, y));
+                self.game.apply(action(x, y));
+                true
+            }
+            _ => false,
         }
+    }
 
-        queue!(self.stdout, ResetColor)?; // Reset colors at the very end
-        self.stdout.flush()
+    /// Moves the cursor to a cell. Returns false if it was already there.
+    fn hover(&mut self, (x, y): (usize, usize)) -> bool {
+        if (x, y) == (self.cursor_x, self.cursor_y) {
+            return false;
+        }
+        (self.cursor_x, self.cursor_y) = (x, y);
+        self.game.log_cursor(x, y);
+        true
     }
 
     pub fn game_loop(&mut self) -> Result<()> {
+        self.display()?;
         loop {
-            self.display()?;
-
-            if let Event::Key(KeyEvent {
-                code,
-                kind: KeyEventKind::Press,
-                ..
-            }) = event::read()?
-            {
-                let is_game_over = self.game.state != GameState::Playing;
-                match code {
-                    KeyCode::Char('q') | KeyCode::Esc => break,
-                    KeyCode::Char('?') => self.display_help()?,
-                    KeyCode::Char('n') if is_game_over => {
-                        self.game = Game::new(
-                            self.game.width,
-                            self.game.height,
-                            self.game.num_mines,
-                            self.game.first_click_policy,
-                        );
+            let Some(event) = self.next_event()? else {
+                self.bot_step();
+                self.record_result();
+                self.display()?;
+                continue;
+            };
+            match event {
+                Event::Key(KeyEvent {
+                    code,
+                    modifiers,
+                    kind: KeyEventKind::Press,
+                    ..
+                }) => {
+                    let is_game_over = self.game.state != GameState::Playing;
+                    self.message = None;
+                    self.hint = None;
+                    match code {
+                        KeyCode::Char('q') | KeyCode::Esc => break,
+                        KeyCode::Char('?') => self.display_help()?,
+                        KeyCode::Char('t') => self.display_stats()?,
+                        KeyCode::Char('s') => self.save(),
+                        KeyCode::Char('w') => self.write_replay(),
+                        KeyCode::Char('u') => self.undo(),
+                        KeyCode::Char('a') => self.toggle_bot(),
+                        KeyCode::Char(' ') if self.bot.running => self.pause_bot(),
+                        KeyCode::Char('+') if self.bot.running => self.bot_speed(0.5),
+                        KeyCode::Char('-') if self.bot.running => self.bot_speed(2.0),
+                        KeyCode::Char('r') if modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.redo()
+                        }
+                        KeyCode::Char('n') if is_game_over => {
+                            self.game = Game::new(
+                                self.game.width,
+                                self.game.height,
+                                self.game.num_mines,
+                                self.game.first_click_policy,
+                            )
+                            .with_topology(self.game.topology())
+                            .with_neighbourhood(self.game.neighbourhood())
+                            .with_depth(self.game.depth());
+                            self.recorded = false;
+                        }
+                        _ if is_game_over => {} // Ignore other input if game over
+                        KeyCode::Left if modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.page(-1, 0)
+                        }
+                        KeyCode::Right if modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.page(1, 0)
+                        }
+                        KeyCode::Up | KeyCode::Char('k') => self.move_cursor(0, -1),
+                        KeyCode::Down | KeyCode::Char('j') => self.move_cursor(0, 1),
+                        KeyCode::Left | KeyCode::Char('h') => self.move_cursor(-1, 0),
+                        KeyCode::Right | KeyCode::Char('l') => self.move_cursor(1, 0),
+                        KeyCode::PageUp => self.page(0, -1),
+                        KeyCode::PageDown => self.page(0, 1),
+                        KeyCode::Home => self.jump_to_column(0),
+                        KeyCode::End => self.jump_to_column(self.game.width - 1),
+                        KeyCode::Char('<') => self.change_layer(-1),
+                        KeyCode::Char('>') => self.change_layer(1),
+                        KeyCode::Char('r') | KeyCode::Enter => {
+                            self.game.reveal(self.cursor_x, self.cursor_y)
+                        }
+                        KeyCode::Char('f') | KeyCode::Char(' ') => {
+                            self.game.flag(self.cursor_x, self.cursor_y)
+                        }
+                        KeyCode::Char('c') => self.game.chord(self.cursor_x, self.cursor_y),
+                        KeyCode::Char('p') => self.toggle_overlay(),
+                        KeyCode::Char('i') => self.hint(),
+                        _ => {}
                     }
-                    _ if is_game_over => {} // Ignore other input if game over
-                    KeyCode::Up | KeyCode::Char('k') => self.move_cursor(0, -1),
-                    KeyCode::Down | KeyCode::Char('j') => self.move_cursor(0, 1),
-                    KeyCode::Left | KeyCode::Char('h') => self.move_cursor(-1, 0),
-                    KeyCode::Right | KeyCode::Char('l') => self.move_cursor(1, 0),
-                    KeyCode::Char('r') | KeyCode::Enter => {
-                        self.game.reveal(self.cursor_x, self.cursor_y)
-                    }
-                    KeyCode::Char('f') | KeyCode::Char(' ') => {
-                        self.game.flag(self.cursor_x, self.cursor_y)
-                    }
-                    _ => {}
                 }
+                Event::Mouse(mouse) if !self.handle_mouse(mouse) => continue,
+                Event::Key(_) => continue, // releases and repeats
+                Event::Resize(columns, rows) => {
+                    self.screen = (columns, rows);
+                    queue!(self.stdout, Clear(ClearType::All))?;
+                }
+                _ => {}
             }
+            self.record_result();
+            self.display()?;
         }
         Ok(())
     }
@@ -291,7 +1090,12 @@ impl Tui {
 
 impl Drop for Tui {
     fn drop(&mut self) {
-        let _ = execute!(self.stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = execute!(
+            self.stdout,
+            event::DisableMouseCapture,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
         let _ = terminal::disable_raw_mode();
     }
 }
@@ -0,0 +1,482 @@
+// Import and export of the community minesweeper video formats:
+//
+// * RAWVF - the plain-text "raw video format" that the video converters
+//   write. Header lines are `Key: Value`, followed by the board (`*` for a
+//   mine, `0` otherwise) and the mouse events:
+//
+//       RawVF_Version: Rev5
+//       Width: 8
+//       Height: 8
+//       Mines: 10
+//       Board:
+//       *0000000
+//       ...
+//       Events:
+//       0.00 start
+//       0.00 lc 40 40 (3 3)
+//       0.12 lr 40 40 (3 3)
+//
+//   Each event is a time in seconds, the event, the mouse position in
+//   pixels and the (column row) of the cell, counted from 1.
+//
+// * AVF - Arbiter's binary format. Only reading is supported.
+//
+// Videos record mouse buttons rather than actions, so the buttons are
+// decoded the way Arbiter plays: a left release reveals, a right press
+// flags, and releasing one button while both are down (or a middle
+// release) chords (see `input::Buttons`).
+
+use crate::game::{Action, LoadGameError, LogEntry, LogEvent, ParseGameError};
+use crate::input::{Button, Buttons};
+use crate::replay::Replay;
+use crate::{Difficulty, Neighbourhood, Topology};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Size of a cell in pixels, for converting mouse positions.
+const CELL_PIXELS: usize = 16;
+
+/// Reads a video file: `.avf` files as AVF, anything else as RAWVF.
+pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Replay, LoadGameError> {
+    let path = path.as_ref();
+    let is_avf = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("avf"));
+    if is_avf {
+        Ok(read_avf(&fs::read(path)?)?)
+    } else {
+        Ok(read_rawvf(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// Turns the mouse events of a video into a move log.
+struct MouseDecoder {
+    width: usize,
+    height: usize,
+    buttons: Buttons,
+    cursor: Option<(usize, usize)>,
+    events: Vec<LogEntry>,
+}
+
+impl MouseDecoder {
+    fn new(width: usize, height: usize) -> Self {
+        MouseDecoder {
+            width,
+            height,
+            buttons: Buttons::default(),
+            cursor: None,
+            events: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, time: Duration, event: LogEvent) {
+        self.events.push(LogEntry { time, event });
+    }
+
+    /// Handles one mouse event. `cell` is `None` for positions off the board.
+    fn mouse(&mut self, time: Duration, event: &str, cell: Option<(usize, usize)>) {
+        let cell = cell.filter(|&(x, y)| x < self.width && y < self.height);
+        if let Some((x, y)) = cell
+            && self.cursor != cell
+        {
+            self.cursor = cell;
+            self.push(time, LogEvent::Cursor(x, y));
+        }
+        let (button, pressed) = match event {
+            "lc" => (Button::Left, true),
+            "lr" => (Button::Left, false),
+            "rc" => (Button::Right, true),
+            "rr" => (Button::Right, false),
+            "mc" => (Button::Middle, true),
+            "mr" => (Button::Middle, false),
+            _ => return, // moves, and non-mouse events like "start"
+        };
+        if let (Some(action), Some((x, y))) = (self.buttons.update(button, pressed), cell) {
+            self.push(time, LogEvent::Action(action(x, y)));
+        }
+    }
+}
+
+fn invalid(field: &str, value: impl Into<String>) -> ParseGameError {
+    ParseGameError::InvalidField {
+        field: field.to_string(),
+        value: value.into(),
+    }
+}
+
+/// Reads a RAWVF video into a replay.
+///
+/// # Errors
+///
+/// Returns an `Err` if the size fields are missing, invalid or 0, the board
+/// doesn't match them, or an event line (or its time) can't be parsed.
+pub fn read_rawvf(text: &str) -> Result<Replay, ParseGameError> {
+    let mut lines = text.lines().map(str::trim);
+
+    let mut width = None;
+    let mut height = None;
+    let mut num_mines = None;
+    for line in lines.by_ref() {
+        if line == "Board:" {
+            break;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        let parse = |v: &str| v.parse::<usize>().map_err(|_| invalid(key, v));
+        let size =
+            |v: &str| parse(v).and_then(|n| if n > 0 { Ok(n) } else { Err(invalid(key, v)) });
+        match key {
+            "Width" => width = Some(size(value)?),
+            "Height" => height = Some(size(value)?),
+            "Mines" => num_mines = Some(parse(value)?),
+            _ => {} // player, program, timestamps etc.
+        }
+    }
+    let width = width.ok_or(ParseGameError::MissingField("Width"))?;
+    let height = height.ok_or(ParseGameError::MissingField("Height"))?;
+    let num_mines = num_mines.ok_or(ParseGameError::MissingField("Mines"))?;
+
+    let mut mines = Vec::with_capacity(width * height);
+    for row_index in 0..height {
+        let row = lines.next().ok_or(ParseGameError::EmptyInput)?;
+        if row.chars().count() != width {
+            return Err(ParseGameError::InconsistentRowLength {
+                expected: width,
+                actual: row.chars().count(),
+                row_index,
+            });
+        }
+        mines.extend(row.chars().map(|c| c == '*'));
+    }
+    let actual = mines.iter().filter(|&&m| m).count();
+    if actual != num_mines {
+        return Err(ParseGameError::MineCountMismatch {
+            expected: num_mines,
+            actual,
+        });
+    }
+
+    let mut decoder = MouseDecoder::new(width, height);
+    for line in lines.skip_while(|&line| line != "Events:").skip(1) {
+        if line.is_empty() {
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let seconds: f64 = parts[0].parse().map_err(|_| invalid("event", line))?;
+        let time =
+            Duration::try_from_secs_f64(seconds.max(0.0)).map_err(|_| invalid("event", line))?; // too large
+        let event = parts.get(1).copied().unwrap_or("");
+        if !matches!(event, "mv" | "lc" | "lr" | "rc" | "rr" | "mc" | "mr") {
+            continue;
+        }
+        // "(col row)" when on the board, else fall back to the pixel position
+        let cell = match parts[2..] {
+            [_, _, col, row] => {
+                let col = col.trim_start_matches('(');
+                let row = row.trim_end_matches(')');
+                match (col.parse::<usize>(), row.parse::<usize>()) {
+                    (Ok(col), Ok(row)) if col > 0 && row > 0 => Some((col - 1, row - 1)),
+                    _ => None,
+                }
+            }
+            [px, py, ..] => {
+                let px: usize = px.parse().map_err(|_| invalid("event", line))?;
+                let py: usize = py.parse().map_err(|_| invalid("event", line))?;
+                Some((px / CELL_PIXELS, py / CELL_PIXELS))
+            }
+            _ => return Err(invalid("event", line)),
+        };
+        decoder.mouse(time, event, cell);
+    }
+
+    Ok(Replay {
+        width,
+        height,
+        mines,
+        topology: Topology::Flat,
+        neighbourhood: Neighbourhood::Moore,
+        depth: 1,
+        events: decoder.events,
+    })
+}
+
+/// Writes a replay as a RAWVF video. Each action becomes a press and
+/// release at the cell (a chord uses the middle button), and cursor moves
+/// become mouse moves. Undo and redo have no equivalent and are left out,
+/// and so are the topology, neighbourhood and layers - videos are always
+/// of the usual flat boards, so a 3D board's layers become one tall board.
+pub fn write_rawvf(replay: &Replay) -> String {
+    let num_mines = replay.mines.iter().filter(|&&m| m).count();
+    let rows = replay.height * replay.depth;
+    let level = Difficulty::from_dimensions(replay.width, rows, num_mines)
+        .map(|d| format!("{d:?}"))
+        .unwrap_or_else(|| "Custom".to_string());
+    let time = replay
+        .events
+        .last()
+        .map(|e| e.time.as_secs_f64())
+        .unwrap_or_default();
+
+    let mut text = format!(
+        "RawVF_Version: Rev5\nProgram: minesweeper-rs\nVersion: {}\nLevel: {level}\nWidth: {}\nHeight: {}\nMines: {num_mines}\nMarks: Off\nTime: {time:.2}\nBoard:\n",
+        env!("CARGO_PKG_VERSION"),
+        replay.width,
+        rows,
+    );
+    for row in replay.mines.chunks(replay.width.max(1)) {
+        text.extend(row.iter().map(|&m| if m { '*' } else { '0' }));
+        text.push('\n');
+    }
+
+    text.push_str("Events:\n0.00 start\n");
+    for entry in &replay.events {
+        let seconds = entry.time.as_secs_f64();
+        let mut mouse = |event: &str, (x, y): (usize, usize)| {
+            let (px, py) = (
+                x * CELL_PIXELS + CELL_PIXELS / 2,
+                y * CELL_PIXELS + CELL_PIXELS / 2,
+            );
+            text.push_str(&format!(
+                "{seconds:.2} {event} {px} {py} ({} {})\n",
+                x + 1,
+                y + 1
+            ));
+        };
+        match entry.event {
+            LogEvent::Cursor(x, y) => mouse("mv", (x, y)),
+            LogEvent::Action(Action::Reveal(x, y)) => {
+                mouse("lc", (x, y));
+                mouse("lr", (x, y));
+            }
+            LogEvent::Action(Action::Flag(x, y)) => {
+                mouse("rc", (x, y));
+                mouse("rr", (x, y));
+            }
+            LogEvent::Action(Action::Chord(x, y)) => {
+                mouse("mc", (x, y));
+                mouse("mr", (x, y));
+            }
+            LogEvent::Undo | LogEvent::Redo => {}
+        }
+    }
+    text
+}
+
+/// A cursor over the bytes of an AVF file.
+struct Bytes<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Bytes<'_> {
+    fn u8(&mut self) -> Result<u8, ParseGameError> {
+        let byte = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| invalid("avf", "unexpected end of file"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn skip_past(&mut self, byte: u8) -> Result<(), ParseGameError> {
+        while self.u8()? != byte {}
+        Ok(())
+    }
+}
+
+/// Reads an Arbiter AVF video into a replay.
+///
+/// The file starts with a version byte, 4 unused bytes and the mode: 3, 4
+/// and 5 are beginner (8x8, 10 mines), intermediate and expert, and 6 is
+/// custom, followed by width - 1, height - 1 and the mine count (16 bits,
+/// big-endian). Then comes (row, column) for each mine, counted from 1, and
+/// a `[...]` text block with player information. The rest is 8-byte mouse
+/// events: the button mask, then the position and time interleaved as
+/// `x_hi sec_lo x_lo hundredths y_hi sec_hi y_lo`, where the seconds are
+/// counted from 1.
+///
+/// # Errors
+///
+/// Returns an `Err` if the file is truncated or has an unknown mode.
+pub fn read_avf(data: &[u8]) -> Result<Replay, ParseGameError> {
+    let mut bytes = Bytes { data, pos: 0 };
+    bytes.u8()?; // version
+    bytes.pos += 4;
+    let (width, height, num_mines) = match bytes.u8()? {
+        3 => (8, 8, 10),
+        4 => (16, 16, 40),
+        5 => (30, 16, 99),
+        6 => {
+            let width = bytes.u8()? as usize + 1;
+            let height = bytes.u8()? as usize + 1;
+            let mines = (bytes.u8()? as usize) << 8 | bytes.u8()? as usize;
+            (width, height, mines)
+        }
+        mode => return Err(invalid("avf mode", mode.to_string())),
+    };
+
+    let mut mines = vec![false; width * height];
+    for _ in 0..num_mines {
+        let row = bytes.u8()? as usize;
+        let col = bytes.u8()? as usize;
+        if row == 0 || col == 0 || row > height || col > width {
+            return Err(invalid("avf mine", format!("({col} {row})")));
+        }
+        mines[(row - 1) * width + col - 1] = true;
+    }
+
+    bytes.skip_past(b'[')?;
+    bytes.skip_past(b']')?;
+
+    let mut decoder = MouseDecoder::new(width, height);
+    while let Some(event) = data.get(bytes.pos..bytes.pos + 8) {
+        bytes.pos += 8;
+        let name = match event[0] {
+            1 => "mv",
+            3 => "lc",
+            5 | 21 => "lr",
+            9 => "rc",
+            17 | 145 => "rr",
+            33 => "mc",
+            65 | 193 => "mr",
+            _ => continue,
+        };
+        let x = (event[1] as usize) << 8 | event[3] as usize;
+        let y = (event[5] as usize) << 8 | event[7] as usize;
+        let seconds = ((event[6] as u64) << 8 | event[2] as u64).saturating_sub(1);
+        let time = Duration::from_millis(seconds * 1000 + event[4] as u64 * 10);
+        // Positions are in pixels, with (1, 1) the top-left corner of the board
+        let cell = (x > 0 && y > 0).then(|| ((x - 1) / CELL_PIXELS, (y - 1) / CELL_PIXELS));
+        decoder.mouse(time, name, cell);
+    }
+
+    Ok(Replay {
+        width,
+        height,
+        mines,
+        topology: Topology::Flat,
+        neighbourhood: Neighbourhood::Moore,
+        depth: 1,
+        events: decoder.events,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+
+    const RAWVF: &str = "RawVF_Version: Rev5
+Program: Arbiter
+Player: Test
+Width: 4
+Height: 3
+Mines: 2
+Board:
+000*
+0000
+000*
+Events:
+0.00 start
+0.00 lc 8 8 (1 1)
+0.05 lr 8 8 (1 1)
+0.50 mv 56 8 (4 1)
+0.60 rc 56 8 (4 1)
+0.65 rr 56 8 (4 1)
+0.90 mv 56 40 (4 3)
+1.00 rc 56 40 (4 3)
+1.05 rr 56 40 (4 3)
+1.20 mv 40 24 (3 2)
+1.30 lc 40 24 (3 2)
+1.35 rc 40 24 (3 2)
+1.40 lr 40 24 (3 2)
+1.45 rr 40 24 (3 2)
+1.45 won
+";
+
+    #[test]
+    fn test_read_rawvf() {
+        let replay = read_rawvf(RAWVF).unwrap();
+        assert_eq!((replay.width, replay.height), (4, 3));
+        let actions: Vec<Action> = replay
+            .events
+            .iter()
+            .filter_map(|e| match e.event {
+                LogEvent::Action(a) => Some(a),
+                _ => None,
+            })
+            .collect();
+        // The last press of both buttons is one chord, not a reveal too
+        assert_eq!(
+            actions,
+            vec![
+                Action::Reveal(0, 0),
+                Action::Flag(3, 0),
+                Action::Flag(3, 2),
+                Action::Chord(2, 1),
+            ]
+        );
+
+        let mut game = replay.new_game().unwrap();
+        for entry in &replay.events {
+            game.apply_log_event(entry.event);
+        }
+        assert_eq!(game.state, GameState::Won);
+    }
+
+    #[test]
+    fn test_rawvf_round_trip() {
+        let replay = read_rawvf(RAWVF).unwrap();
+        let written = write_rawvf(&replay);
+        let reread = read_rawvf(&written).unwrap();
+        assert_eq!(reread.mines, replay.mines);
+        let events = |r: &Replay| r.events.iter().map(|e| e.event).collect::<Vec<_>>();
+        assert_eq!(events(&reread), events(&replay));
+    }
+
+    #[test]
+    fn test_read_rawvf_rejects_bad_input() {
+        let zero_width = RAWVF.replacen("Width: 4", "Width: 0", 1);
+        assert!(matches!(
+            read_rawvf(&zero_width),
+            Err(ParseGameError::InvalidField { .. })
+        ));
+        let zero_height = RAWVF.replacen("Height: 3", "Height: 0", 1);
+        assert!(matches!(
+            read_rawvf(&zero_height),
+            Err(ParseGameError::InvalidField { .. })
+        ));
+        let text = RAWVF.replacen("1.45 won", "1e300 lr 40 24 (3 2)", 1);
+        assert!(matches!(
+            read_rawvf(&text),
+            Err(ParseGameError::InvalidField { .. })
+        ));
+        assert!(read_rawvf(&RAWVF.replacen("1.45 won", "inf mv 40 24 (3 2)", 1)).is_err());
+    }
+
+    #[test]
+    fn test_read_avf() {
+        // Custom 4x3 board, mines at (4,1) and (4,3), and a left click on (1,1)
+        let mut data = vec![1, 0, 0, 0, 0, 6, 3, 2, 0, 2, 1, 4, 3, 4];
+        data.extend(b"[0|Test]");
+        data.extend([3, 0, 1, 8, 0, 0, 0, 8]); // lc at pixel (8,8), 0.00s
+        data.extend([5, 0, 1, 8, 5, 0, 0, 8]); // lr at pixel (8,8), 0.05s
+        let replay = read_avf(&data).unwrap();
+        assert_eq!((replay.width, replay.height), (4, 3));
+        assert_eq!(replay.mines.iter().filter(|&&m| m).count(), 2);
+        assert!(replay.mines[3] && replay.mines[11]);
+        assert_eq!(
+            replay.events.last().unwrap().event,
+            LogEvent::Action(Action::Reveal(0, 0))
+        );
+        assert_eq!(
+            replay.events.last().unwrap().time,
+            Duration::from_millis(50)
+        );
+
+        assert!(read_avf(&data[..10]).is_err());
+    }
+}